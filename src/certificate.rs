@@ -0,0 +1,466 @@
+//! X.509 certificate parsing.
+//!
+//! Certificates are decoded in-process from PEM or DER, so expiry checks work
+//! in minimal images without an `openssl` binary and never depend on the
+//! formatting of CLI output.
+
+use crate::{Error, san::SubjectAltName};
+use aws_lc_rs::digest;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use chrono::{DateTime, Utc};
+use std::{fmt, net::IpAddr};
+use x509_parser::{
+    certificate::X509Certificate,
+    extensions::GeneralName,
+    objects::{oid_registry, oid2sn},
+    oid_registry::{
+        OID_EC_P256, OID_KEY_TYPE_EC_PUBLIC_KEY, OID_NIST_EC_P384, OID_NIST_EC_P521,
+        OID_PKCS1_RSAENCRYPTION, OID_SIG_ED448, OID_SIG_ED25519,
+    },
+    pem::Pem,
+    prelude::FromDer,
+    time::ASN1Time,
+    x509::SubjectPublicKeyInfo,
+};
+
+/// Validity window of a certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Validity {
+    /// Start of the validity period (`notBefore`).
+    pub not_before: DateTime<Utc>,
+    /// End of the validity period (`notAfter`).
+    pub not_after: DateTime<Utc>,
+}
+
+impl Validity {
+    /// Time left until `notAfter`, negative once the certificate has expired.
+    #[must_use]
+    pub fn remaining(&self, now: DateTime<Utc>) -> chrono::Duration {
+        self.not_after - now
+    }
+
+    /// The moment `percent` of the lifetime (`notBefore` to `notAfter`) has
+    /// elapsed.
+    #[must_use]
+    pub fn lifetime_point(&self, percent: u8) -> DateTime<Utc> {
+        let lifetime = (self.not_after - self.not_before).num_seconds();
+        self.not_before + chrono::Duration::seconds(lifetime * i64::from(percent) / 100)
+    }
+
+    /// Whether `now` falls inside the validity window.
+    #[must_use]
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        self.not_before <= now && now <= self.not_after
+    }
+}
+
+/// Public key algorithm of a certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum KeyAlgorithm {
+    /// RSA of any modulus size.
+    Rsa,
+    /// ECDSA on NIST P-256.
+    EcdsaP256,
+    /// ECDSA on NIST P-384.
+    EcdsaP384,
+    /// ECDSA on NIST P-521.
+    EcdsaP521,
+    /// Ed25519.
+    Ed25519,
+    /// Ed448.
+    Ed448,
+    /// Any other algorithm, identified by its dotted OID.
+    Other(String),
+}
+
+impl fmt::Display for KeyAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Rsa => f.write_str("RSA"),
+            Self::EcdsaP256 => f.write_str("ECDSA P-256"),
+            Self::EcdsaP384 => f.write_str("ECDSA P-384"),
+            Self::EcdsaP521 => f.write_str("ECDSA P-521"),
+            Self::Ed25519 => f.write_str("Ed25519"),
+            Self::Ed448 => f.write_str("Ed448"),
+            Self::Other(oid) => write!(f, "unknown ({oid})"),
+        }
+    }
+}
+
+/// Summary of a deployed certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertificateInfo {
+    /// Subject distinguished name, e.g. `CN=api.example.com, O=Example`.
+    pub subject: String,
+    /// Issuer distinguished name.
+    pub issuer: String,
+    /// Serial number as colon-separated hex.
+    pub serial: String,
+    /// DNS, IP, URI and email subject alternative names.
+    pub subject_alt_names: Vec<SubjectAltName>,
+    /// Validity window.
+    pub validity: Validity,
+    /// Public key algorithm.
+    pub key_algorithm: KeyAlgorithm,
+    /// Public key size in bits.
+    pub key_bits: u32,
+    /// Signature algorithm name, e.g. `ecdsa-with-SHA256`.
+    pub signature_algorithm: String,
+    /// SHA-256 fingerprint of the DER certificate as lowercase hex.
+    pub fingerprint_sha256: String,
+    /// Base64 SHA-256 digest of the `SubjectPublicKeyInfo` (`pin-sha256`).
+    pub spki_pin_sha256: String,
+}
+
+impl CertificateInfo {
+    /// Whether the certificate names itself as issuer, as self-signed ones do.
+    #[must_use]
+    pub fn is_self_issued(&self) -> bool {
+        self.issuer == self.subject
+    }
+}
+
+/// Parse a PEM or DER encoded certificate into a [`CertificateInfo`].
+///
+/// For PEM input containing a chain, the first `CERTIFICATE` block (the leaf)
+/// is used.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the data is not a well-formed certificate.
+pub fn parse_certificate_info(data: &[u8]) -> crate::Result<CertificateInfo> {
+    with_leaf(data, |cert| {
+        let validity = cert.validity();
+        let (key_algorithm, key_bits) = key_algorithm_of(cert.public_key());
+
+        let subject_alt_names = cert
+            .subject_alternative_name()
+            .map_err(|e| Error::Parse(format!("Invalid subjectAltName extension: {e}")))?
+            .map(|ext| {
+                ext.value
+                    .general_names
+                    .iter()
+                    .filter_map(san_from_general_name)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let signature_oid = &cert.signature_algorithm.algorithm;
+        let signature_algorithm = oid2sn(signature_oid, oid_registry())
+            .map_or_else(|_| signature_oid.to_id_string(), str::to_owned);
+
+        Ok(CertificateInfo {
+            subject: cert.subject().to_string(),
+            issuer: cert.issuer().to_string(),
+            serial: cert.raw_serial_as_string(),
+            subject_alt_names,
+            validity: Validity {
+                not_before: to_utc(validity.not_before)?,
+                not_after: to_utc(validity.not_after)?,
+            },
+            key_algorithm,
+            key_bits,
+            signature_algorithm,
+            fingerprint_sha256: to_hex(digest::digest(&digest::SHA256, cert.as_raw()).as_ref()),
+            spki_pin_sha256: BASE64
+                .encode(digest::digest(&digest::SHA256, cert.public_key().raw).as_ref()),
+        })
+    })
+}
+
+/// Parse the validity window of a PEM or DER encoded certificate.
+///
+/// For PEM input containing a chain, the first `CERTIFICATE` block (the leaf)
+/// is used.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the data is not a well-formed certificate.
+pub fn parse_validity(data: &[u8]) -> crate::Result<Validity> {
+    with_leaf(data, |cert| {
+        let validity = cert.validity();
+        Ok(Validity {
+            not_before: to_utc(validity.not_before)?,
+            not_after: to_utc(validity.not_after)?,
+        })
+    })
+}
+
+/// Check whether a PEM private key belongs to the leaf certificate in `cert`.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if either the certificate or the key cannot be
+/// parsed.
+pub fn key_matches(cert: &[u8], key: &[u8]) -> crate::Result<bool> {
+    let key_public = crate::key::public_key_from_pem(key)?;
+    with_leaf(cert, |cert| {
+        Ok(cert.public_key().subject_public_key.data.as_ref() == key_public.as_slice())
+    })
+}
+
+/// Decode the leaf certificate in `data` and hand it to `f`.
+pub(crate) fn with_leaf<T>(
+    data: &[u8],
+    f: impl FnOnce(&X509Certificate<'_>) -> crate::Result<T>,
+) -> crate::Result<T> {
+    if is_der(data) {
+        let (_, cert) = X509Certificate::from_der(data)
+            .map_err(|e| Error::Parse(format!("Invalid DER certificate: {e}")))?;
+        return f(&cert);
+    }
+
+    for pem in Pem::iter_from_buffer(data) {
+        let pem = pem.map_err(|e| Error::Parse(format!("Invalid PEM data: {e}")))?;
+        if pem.label == "CERTIFICATE" {
+            let cert = pem
+                .parse_x509()
+                .map_err(|e| Error::Parse(format!("Invalid PEM certificate: {e}")))?;
+            return f(&cert);
+        }
+    }
+
+    Err(Error::Parse(
+        "No CERTIFICATE block found in PEM data".to_owned(),
+    ))
+}
+
+/// DER certificates always start with a SEQUENCE tag.
+fn is_der(data: &[u8]) -> bool {
+    data.first() == Some(&0x30)
+}
+
+fn key_algorithm_of(spki: &SubjectPublicKeyInfo<'_>) -> (KeyAlgorithm, u32) {
+    let oid = &spki.algorithm.algorithm;
+
+    if *oid == OID_PKCS1_RSAENCRYPTION {
+        let bits = match spki.parsed() {
+            Ok(x509_parser::public_key::PublicKey::RSA(rsa)) => integer_bits(rsa.modulus),
+            _ => 0,
+        };
+        (KeyAlgorithm::Rsa, bits)
+    } else if *oid == OID_KEY_TYPE_EC_PUBLIC_KEY {
+        let curve = spki
+            .algorithm
+            .parameters
+            .as_ref()
+            .and_then(|params| params.as_oid().ok());
+        match curve {
+            Some(curve) if curve == OID_EC_P256 => (KeyAlgorithm::EcdsaP256, 256),
+            Some(curve) if curve == OID_NIST_EC_P384 => (KeyAlgorithm::EcdsaP384, 384),
+            Some(curve) if curve == OID_NIST_EC_P521 => (KeyAlgorithm::EcdsaP521, 521),
+            Some(curve) => (KeyAlgorithm::Other(curve.to_id_string()), 0),
+            None => (KeyAlgorithm::Other(oid.to_id_string()), 0),
+        }
+    } else if *oid == OID_SIG_ED25519 {
+        (KeyAlgorithm::Ed25519, 256)
+    } else if *oid == OID_SIG_ED448 {
+        (KeyAlgorithm::Ed448, 456)
+    } else {
+        (KeyAlgorithm::Other(oid.to_id_string()), 0)
+    }
+}
+
+/// Bit length of a big-endian unsigned integer.
+fn integer_bits(bytes: &[u8]) -> u32 {
+    let Some(pos) = bytes.iter().position(|&b| b != 0) else {
+        return 0;
+    };
+    let significant = u32::try_from(bytes.len() - pos).unwrap_or(u32::MAX / 8);
+    let leading = bytes.get(pos).map_or(0, |b| b.leading_zeros());
+    significant * 8 - leading
+}
+
+fn san_from_general_name(name: &GeneralName<'_>) -> Option<SubjectAltName> {
+    match name {
+        GeneralName::DNSName(dns) => Some(SubjectAltName::Dns((*dns).to_owned())),
+        GeneralName::URI(uri) => Some(SubjectAltName::Uri((*uri).to_owned())),
+        GeneralName::RFC822Name(email) => Some(SubjectAltName::Email((*email).to_owned())),
+        GeneralName::IPAddress(bytes) => {
+            let addr = <[u8; 4]>::try_from(*bytes)
+                .map(IpAddr::from)
+                .or_else(|_| <[u8; 16]>::try_from(*bytes).map(IpAddr::from))
+                .ok()?;
+            Some(SubjectAltName::Ip(addr))
+        }
+        _ => None,
+    }
+}
+
+/// Lowercase hex encoding without separators.
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut out, b| {
+            let _ = write!(out, "{b:02x}");
+            out
+        })
+}
+
+fn to_utc(time: ASN1Time) -> crate::Result<DateTime<Utc>> {
+    DateTime::from_timestamp(time.timestamp(), 0)
+        .ok_or_else(|| Error::Parse(format!("Certificate time out of range: {time}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use rcgen::{CertificateParams, KeyPair, PublicKeyData};
+    use time::OffsetDateTime;
+
+    fn generate_cert(not_before: DateTime<Utc>, not_after: DateTime<Utc>) -> rcgen::Certificate {
+        let mut params = CertificateParams::new(vec!["test.example.com".to_owned()]).unwrap();
+        params.not_before = OffsetDateTime::from_unix_timestamp(not_before.timestamp()).unwrap();
+        params.not_after = OffsetDateTime::from_unix_timestamp(not_after.timestamp()).unwrap();
+        let key = KeyPair::generate().unwrap();
+        params.self_signed(&key).unwrap()
+    }
+
+    #[test]
+    fn test_lifetime_point() {
+        let validity = Validity {
+            not_before: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            not_after: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+        };
+
+        assert_eq!(validity.lifetime_point(0), validity.not_before);
+        assert_eq!(
+            validity.lifetime_point(67),
+            validity.not_before + Duration::seconds(57_888)
+        );
+        assert_eq!(validity.lifetime_point(100), validity.not_after);
+    }
+
+    #[test]
+    fn test_parse_validity_pem() {
+        let not_before = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let not_after = Utc.with_ymd_and_hms(2024, 1, 16, 12, 30, 0).unwrap();
+        let cert = generate_cert(not_before, not_after);
+
+        let validity = parse_validity(cert.pem().as_bytes()).unwrap();
+        assert_eq!(validity.not_before, not_before);
+        assert_eq!(validity.not_after, not_after);
+    }
+
+    #[test]
+    fn test_parse_validity_der() {
+        let not_before = Utc.with_ymd_and_hms(2030, 6, 1, 0, 0, 0).unwrap();
+        let not_after = Utc.with_ymd_and_hms(2060, 6, 1, 0, 0, 0).unwrap();
+        let cert = generate_cert(not_before, not_after);
+
+        let validity = parse_validity(cert.der()).unwrap();
+        assert_eq!(validity.not_before, not_before);
+        assert_eq!(validity.not_after, not_after);
+    }
+
+    #[test]
+    fn test_parse_validity_uses_leaf_of_chain() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let leaf = generate_cert(now, now + Duration::days(1));
+        let issuer = generate_cert(now, now + Duration::days(3650));
+        let chain = format!("{}{}", leaf.pem(), issuer.pem());
+
+        let validity = parse_validity(chain.as_bytes()).unwrap();
+        assert_eq!(validity.not_after, now + Duration::days(1));
+    }
+
+    #[test]
+    fn test_parse_validity_rejects_garbage() {
+        assert!(parse_validity(b"").is_err());
+        assert!(parse_validity(b"not a certificate").is_err());
+        assert!(
+            parse_validity(b"-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n")
+                .is_err()
+        );
+        assert!(parse_validity(&[0x30, 0x03, 0x01, 0x02]).is_err());
+    }
+
+    #[test]
+    fn test_parse_certificate_info() {
+        let mut params = CertificateParams::new(vec![
+            "api.example.com".to_owned(),
+            "192.168.1.10".to_owned(),
+            "::1".to_owned(),
+        ])
+        .unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "api.example.com");
+        let key = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+
+        let info = parse_certificate_info(cert.pem().as_bytes()).unwrap();
+        assert_eq!(info.subject, "CN=api.example.com");
+        assert_eq!(info.issuer, info.subject);
+        assert_eq!(
+            info.subject_alt_names,
+            vec![
+                SubjectAltName::Dns("api.example.com".to_owned()),
+                SubjectAltName::Ip("192.168.1.10".parse().unwrap()),
+                SubjectAltName::Ip("::1".parse().unwrap()),
+            ]
+        );
+        assert_eq!(info.key_algorithm, KeyAlgorithm::EcdsaP256);
+        assert_eq!(info.key_bits, 256);
+        assert_eq!(info.signature_algorithm, "ecdsa-with-SHA256");
+        assert_eq!(
+            info.fingerprint_sha256,
+            to_hex(digest::digest(&digest::SHA256, cert.der()).as_ref())
+        );
+        assert_eq!(
+            info.spki_pin_sha256,
+            BASE64.encode(digest::digest(&digest::SHA256, &key.subject_public_key_info()).as_ref())
+        );
+        assert!(!info.serial.is_empty());
+    }
+
+    #[test]
+    fn test_parse_certificate_info_ed25519() {
+        let params = CertificateParams::new(vec!["edge.example.com".to_owned()]).unwrap();
+        let key = KeyPair::generate_for(&rcgen::PKCS_ED25519).unwrap();
+        let cert = params.self_signed(&key).unwrap();
+
+        let info = parse_certificate_info(cert.der()).unwrap();
+        assert_eq!(info.key_algorithm, KeyAlgorithm::Ed25519);
+        assert_eq!(info.key_bits, 256);
+    }
+
+    #[test]
+    fn test_key_matches() {
+        let params = CertificateParams::new(vec!["api.example.com".to_owned()]).unwrap();
+        let key = KeyPair::generate().unwrap();
+        let other = KeyPair::generate().unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        let pem = cert.pem();
+
+        assert!(key_matches(pem.as_bytes(), key.serialize_pem().as_bytes()).unwrap());
+        assert!(!key_matches(pem.as_bytes(), other.serialize_pem().as_bytes()).unwrap());
+        assert!(key_matches(pem.as_bytes(), b"not a key").is_err());
+    }
+
+    #[test]
+    fn test_integer_bits() {
+        assert_eq!(integer_bits(&[]), 0);
+        assert_eq!(integer_bits(&[0x00, 0x80, 0x00]), 16);
+        assert_eq!(integer_bits(&[0x01, 0xff]), 9);
+        assert_eq!(integer_bits(&[0xff; 256]), 2048);
+    }
+
+    #[test]
+    fn test_validity_remaining_and_contains() {
+        let not_before = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let validity = Validity {
+            not_before,
+            not_after: not_before + Duration::days(10),
+        };
+
+        let now = not_before + Duration::days(4);
+        assert_eq!(validity.remaining(now), Duration::days(6));
+        assert!(validity.contains(now));
+        assert!(!validity.contains(not_before - Duration::seconds(1)));
+        assert!(validity.remaining(not_before + Duration::days(11)) < Duration::zero());
+    }
+}
@@ -0,0 +1,175 @@
+//! Human-readable durations such as `36h`, `90m` or `7d`.
+
+use chrono::Duration;
+use std::fmt::Write;
+
+/// Units accepted in durations, largest first, with their length in seconds.
+const UNITS: [(char, i64); 4] = [('d', 86_400), ('h', 3_600), ('m', 60), ('s', 1)];
+
+/// Parse a duration made of `<number><unit>` parts with units `d`, `h`, `m`
+/// and `s`, e.g. `7d`, `36h` or `1h30m`.
+///
+/// A bare number counts in `unit`, so settings that used to be whole seconds
+/// or days keep their meaning.
+///
+/// # Errors
+///
+/// Returns error if the text is not a duration or is too long to represent.
+pub fn parse(text: &str, unit: Duration) -> Result<Duration, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("expected a duration such as 36h, 90m or 7d".to_owned());
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text
+            .parse::<i64>()
+            .ok()
+            .and_then(|count| count.checked_mul(unit.num_seconds()))
+            .and_then(Duration::try_seconds)
+            .ok_or_else(|| format!("duration '{text}' is too long"));
+    }
+
+    let mut seconds: i64 = 0;
+    let mut digits = String::new();
+    for c in text.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+
+        let Some((_, length)) = UNITS.iter().find(|(name, _)| *name == c) else {
+            return Err(format!(
+                "unknown unit '{c}' in duration '{text}' (use d, h, m or s)"
+            ));
+        };
+        if digits.is_empty() {
+            return Err(format!("missing number before '{c}' in duration '{text}'"));
+        }
+        seconds = digits
+            .parse::<i64>()
+            .ok()
+            .and_then(|count| count.checked_mul(*length))
+            .and_then(|part| seconds.checked_add(part))
+            .ok_or_else(|| format!("duration '{text}' is too long"))?;
+        digits.clear();
+    }
+    if !digits.is_empty() {
+        return Err(format!(
+            "missing unit after '{digits}' in duration '{text}'"
+        ));
+    }
+
+    Duration::try_seconds(seconds).ok_or_else(|| format!("duration '{text}' is too long"))
+}
+
+/// Format a duration exactly with the largest units, e.g. `1d12h` or `1h30m`,
+/// in a form [`parse`] reads back.
+#[must_use]
+pub fn format(duration: Duration) -> String {
+    render(duration, UNITS.len())
+}
+
+/// Format a duration with at most two units, e.g. `14d23h` for
+/// a certificate that expires in 14 days, 23 hours and 59 minutes.
+#[must_use]
+pub fn format_approx(duration: Duration) -> String {
+    render(duration, 2)
+}
+
+/// Format a duration the way Go's `time.ParseDuration` reads it, e.g. `36h`
+/// or `1h30m`, as expected by `step` and step-ca.
+#[must_use]
+pub fn format_go(duration: Duration) -> String {
+    let seconds = duration.num_seconds().unsigned_abs();
+    let parts = [
+        (seconds / 3_600, 'h'),
+        (seconds % 3_600 / 60, 'm'),
+        (seconds % 60, 's'),
+    ];
+    join(
+        &parts
+            .into_iter()
+            .filter(|(count, _)| *count != 0)
+            .collect::<Vec<_>>(),
+    )
+}
+
+fn render(duration: Duration, max_parts: usize) -> String {
+    let seconds = duration.num_seconds();
+    let mut rest = seconds.unsigned_abs();
+    let mut parts = Vec::new();
+    for (unit, length) in UNITS {
+        let length = length.unsigned_abs();
+        if rest >= length && parts.len() < max_parts {
+            parts.push((rest / length, unit));
+            rest %= length;
+        }
+    }
+
+    let text = join(&parts);
+    if seconds < 0 {
+        format!("-{text}")
+    } else {
+        text
+    }
+}
+
+/// `[(1, 'h'), (30, 'm')]` as `1h30m`, or `0s` without parts.
+fn join(parts: &[(u64, char)]) -> String {
+    if parts.is_empty() {
+        return "0s".to_owned();
+    }
+    parts.iter().fold(String::new(), |mut text, (count, unit)| {
+        let _ = write!(text, "{count}{unit}");
+        text
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let day = Duration::days(1);
+        assert_eq!(parse("36h", day), Ok(Duration::hours(36)));
+        assert_eq!(parse("90m", day), Ok(Duration::minutes(90)));
+        assert_eq!(parse(" 7d ", day), Ok(Duration::days(7)));
+        assert_eq!(parse("1h30m15s", day), Ok(Duration::seconds(5_415)));
+        assert_eq!(parse("5", day), Ok(Duration::days(5)));
+        assert_eq!(
+            parse("600", Duration::seconds(1)),
+            Ok(Duration::minutes(10))
+        );
+
+        assert!(parse("", day).is_err());
+        assert!(parse("1x", day).is_err());
+        assert!(parse("h", day).is_err());
+        assert!(parse("1h30", day).is_err());
+        assert!(parse("-1h", day).is_err());
+        assert!(parse("-5", day).is_err());
+        assert!(parse("99999999999999999d", day).is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(format(Duration::days(7)), "7d");
+        assert_eq!(format(Duration::hours(36)), "1d12h");
+        assert_eq!(format(Duration::minutes(90)), "1h30m");
+        assert_eq!(format(Duration::seconds(86_401)), "1d1s");
+        assert_eq!(format(Duration::zero()), "0s");
+        assert_eq!(format(Duration::hours(-2)), "-2h");
+
+        let remaining = Duration::days(14) + Duration::hours(23) + Duration::minutes(59);
+        assert_eq!(format_approx(remaining), "14d23h");
+        assert_eq!(format_approx(Duration::seconds(30)), "30s");
+
+        assert_eq!(format_go(Duration::days(15)), "360h");
+        assert_eq!(format_go(Duration::minutes(90)), "1h30m");
+        assert_eq!(format_go(Duration::zero()), "0s");
+
+        for text in ["7d", "1d12h", "1h30m", "1d1s"] {
+            assert_eq!(format(parse(text, Duration::days(1)).unwrap()), text);
+        }
+    }
+}
@@ -0,0 +1,172 @@
+//! Error type of the library.
+//!
+//! Each variant is one failure domain, so callers can match on what went
+//! wrong and the daemon can tell failures that clear up by themselves from
+//! ones someone has to fix.
+
+use crate::settings::ConfigError;
+use crate::step_ca::StepCaError;
+use thiserror::Error;
+
+/// Result of a library operation.
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// Failure of a certificate management operation.
+#[derive(Debug, Error)]
+pub enum Error {
+    /// The configuration is invalid or cannot be read.
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    /// A file or directory could not be read or written.
+    #[error("{path}: {source}")]
+    Io {
+        /// Path of the file or directory.
+        path: String,
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+    /// A certificate or key is malformed.
+    #[error("{0}")]
+    Parse(String),
+    /// A backend failed to issue or renew the certificate.
+    #[error("{backend}: {source}")]
+    Issuance {
+        /// Backend that was asked, e.g. `step-ca` or `self-signed`.
+        backend: String,
+        /// What the backend reported.
+        source: Box<dyn std::error::Error + Send + Sync>,
+        /// Whether the backend may succeed later, e.g. because the CA was
+        /// unreachable rather than rejected the credentials.
+        retryable: bool,
+    },
+    /// The new certificate could not be moved into place.
+    #[error("Failed to deploy certificate to {path}: {source}")]
+    Deploy {
+        /// Destination of the certificate or key.
+        path: String,
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+    /// The reload command failed after the certificate was deployed. It is
+    /// run again at the next check.
+    #[error("Reload command '{command}' failed: {reason}")]
+    Reload {
+        /// Command as configured.
+        command: String,
+        /// Exit status or output of the command.
+        reason: String,
+    },
+    /// A notification could not be delivered.
+    #[error("{channel} notification failed: {reason}")]
+    Notification {
+        /// Channel the notification was sent to, e.g. `Slack`.
+        channel: String,
+        /// Why delivery failed.
+        reason: String,
+    },
+}
+
+impl Error {
+    /// Whether the operation may succeed when tried again unchanged.
+    ///
+    /// A CA or webhook that is unreachable or overloaded is retried at the
+    /// next check. Every other failure needs someone to act and is alerted
+    /// on, including a CA rejecting the credentials or serving another root.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            Self::Issuance {
+                retryable: true,
+                ..
+            } | Self::Notification { .. }
+        )
+    }
+
+    /// Attach `path` to an I/O error, for use with `map_err`.
+    pub(crate) fn io(path: &str) -> impl FnOnce(std::io::Error) -> Self {
+        let path = path.to_owned();
+        move |source| Self::Io { path, source }
+    }
+
+    /// Attribute a failure that may clear up by itself to the issuance
+    /// `backend`.
+    pub(crate) fn issuance(
+        backend: &str,
+        source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::Issuance {
+            backend: backend.to_owned(),
+            source: source.into(),
+            retryable: true,
+        }
+    }
+}
+
+impl From<StepCaError> for Error {
+    fn from(error: StepCaError) -> Self {
+        Self::Issuance {
+            backend: "step-ca".to_owned(),
+            retryable: error.is_retryable(),
+            source: error.into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retryable_errors() {
+        let unreachable = Error::from(StepCaError::InvalidResponse("empty body".to_owned()));
+        assert!(unreachable.is_retryable());
+        assert_eq!(
+            unreachable.to_string(),
+            "step-ca: Invalid Step CA response: empty body"
+        );
+        let overloaded = StepCaError::Api {
+            status: 503,
+            message: "service unavailable".to_owned(),
+        };
+        assert!(Error::from(overloaded).is_retryable());
+        assert!(
+            Error::Notification {
+                channel: "Slack".to_owned(),
+                reason: "503 Service Unavailable".to_owned(),
+            }
+            .is_retryable()
+        );
+
+        // Credentials and roots the CA rejects stay rejected
+        let unauthorized = StepCaError::Api {
+            status: 401,
+            message: "token is expired".to_owned(),
+        };
+        assert!(!Error::from(unauthorized).is_retryable());
+        let mismatch = StepCaError::RootMismatch {
+            expected: "aa".to_owned(),
+            actual: "bb".to_owned(),
+        };
+        assert!(!Error::from(mismatch).is_retryable());
+        let password = StepCaError::File {
+            path: "/secrets/password".to_owned(),
+            source: std::io::ErrorKind::NotFound.into(),
+        };
+        assert!(!Error::from(password).is_retryable());
+
+        let config = Error::from(ConfigError::from("CHECK_INTERVAL=\"0\": must not be zero"));
+        assert!(!config.is_retryable());
+        let missing =
+            Error::io("/certs/api.crt")(std::io::Error::from(std::io::ErrorKind::PermissionDenied));
+        assert!(!missing.is_retryable());
+        assert!(missing.to_string().starts_with("/certs/api.crt: "));
+        assert!(
+            !Error::Reload {
+                command: "nginx -s reload".to_owned(),
+                reason: "exit status: 1".to_owned(),
+            }
+            .is_retryable()
+        );
+    }
+}
@@ -0,0 +1,402 @@
+//! Certificate issuers.
+//!
+//! An [`Issuer`] turns an [`IssueRequest`] into a certificate, its chain and
+//! its key. The manager asks each issuer of its chain in order and deploys the
+//! first certificate it gets, so a backend outside this crate can be added by
+//! implementing the trait and putting it in [`CertManager::issuers`].
+//!
+//! [`CertManager::issuers`]: crate::CertManager::issuers
+
+pub mod self_signed;
+pub mod step_ca;
+pub mod step_cli;
+
+pub use self_signed::SelfSignedIssuer;
+pub use step_ca::StepCaIssuer;
+pub use step_cli::StepCliIssuer;
+
+use crate::{CertStatus, Config, Result, key::KeyType, profile::CertProfile, san::SubjectAltName};
+use chrono::Duration;
+use futures::future::BoxFuture;
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// A backend that issues certificates.
+pub trait Issuer: Send + Sync {
+    /// Name used in logs and errors, e.g. `step-ca`.
+    fn name(&self) -> &str;
+
+    /// Whether clients trust the certificates this issuer returns.
+    ///
+    /// An untrusted issuer placed after a trusted one in the chain is a
+    /// fallback: it is only asked as [`FallbackPolicy`] allows, and what it
+    /// issues is replaced as soon as a trusted issuer succeeds again.
+    fn is_trusted(&self) -> bool {
+        true
+    }
+
+    /// Issue a certificate for `request`.
+    ///
+    /// The issuer must not touch the deployed files; the manager deploys what
+    /// it returns.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the backend cannot issue the certificate, after which
+    /// the next issuer of the chain is asked.
+    fn issue<'a>(&'a self, request: &'a IssueRequest) -> BoxFuture<'a, Result<IssuedCert>>;
+}
+
+/// What a new certificate should look like.
+#[derive(Debug, Clone)]
+pub struct IssueRequest {
+    /// Name of the managed certificate, e.g. `api-gateway`.
+    pub name: String,
+    /// Subject common name.
+    pub common_name: String,
+    /// Subject alternative names.
+    pub subject_alt_names: Vec<SubjectAltName>,
+    /// Validity period, starting now.
+    pub validity: Duration,
+    /// Algorithm of the new key.
+    pub key_type: KeyType,
+    /// Subject fields, usages and policies.
+    pub profile: CertProfile,
+    /// Directory the certificate is deployed to, where issuers may keep
+    /// their own files.
+    pub cert_dir: String,
+    /// The deployed certificate, when it is still valid and only due for
+    /// renewal, so issuers that can renew with it may do so.
+    pub current: Option<CurrentCert>,
+}
+
+impl IssueRequest {
+    /// Path of a working file next to the deployed certificate, e.g.
+    /// `{cert_dir}/{name}-profile.json` for `profile.json`.
+    #[must_use]
+    pub fn work_file(&self, suffix: &str) -> String {
+        format!("{}/{}-{suffix}", self.cert_dir, self.name)
+    }
+}
+
+/// Location of the deployed certificate and key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrentCert {
+    /// PEM certificate, followed by its chain.
+    pub cert_path: String,
+    /// PEM private key.
+    pub key_path: String,
+}
+
+/// A certificate returned by an issuer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuedCert {
+    /// PEM leaf certificate.
+    pub cert_pem: String,
+    /// PEM intermediate certificates, empty for a self-signed certificate.
+    pub chain_pem: String,
+    /// PEM private key of the leaf.
+    pub key_pem: String,
+}
+
+impl IssuedCert {
+    /// Split a PEM bundle that starts with the leaf into certificate and chain.
+    #[must_use]
+    pub fn from_bundle(bundle: &str, key_pem: String) -> Self {
+        const END: &str = "-----END CERTIFICATE-----";
+
+        let (cert_pem, chain_pem) = bundle.find(END).map_or_else(
+            || (bundle.to_owned(), String::new()),
+            |end| {
+                let (leaf, chain) = bundle.split_at(end + END.len());
+                (format!("{leaf}\n"), chain.trim_start().to_owned())
+            },
+        );
+        Self {
+            cert_pem,
+            chain_pem,
+            key_pem,
+        }
+    }
+
+    /// The leaf followed by its chain, as deployed.
+    #[must_use]
+    pub fn full_chain_pem(&self) -> String {
+        format!("{}{}", self.cert_pem, self.chain_pem)
+    }
+}
+
+/// Issuers built into this crate, as named in `ISSUERS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IssuerKind {
+    /// Enrollment and renewal with the configured step-ca server.
+    StepCa,
+    /// A local leaf from `step certificate create`.
+    StepCli,
+    /// A self-signed certificate generated in-process.
+    SelfSigned,
+}
+
+impl IssuerKind {
+    /// All built-in issuers.
+    pub const ALL: [Self; 3] = [Self::StepCa, Self::StepCli, Self::SelfSigned];
+
+    /// Canonical configuration name, e.g. `step-ca`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::StepCa => "step-ca",
+            Self::StepCli => "step-cli",
+            Self::SelfSigned => "self-signed",
+        }
+    }
+
+    /// The chain used when `ISSUERS` is not set.
+    #[must_use]
+    pub fn default_chain(step_ca: bool) -> Vec<Self> {
+        if step_ca {
+            vec![Self::StepCa, Self::SelfSigned]
+        } else {
+            vec![Self::StepCli, Self::SelfSigned]
+        }
+    }
+
+    /// Parse a comma-separated list of issuers, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the list is empty or names an unknown or repeated issuer.
+    pub fn parse_chain(value: &str) -> std::result::Result<Vec<Self>, String> {
+        let mut chain = Vec::new();
+        for name in value.split(',').filter(|name| !name.trim().is_empty()) {
+            let kind: Self = name.parse()?;
+            if chain.contains(&kind) {
+                return Err(format!("{kind} is listed twice"));
+            }
+            chain.push(kind);
+        }
+        if chain.is_empty() {
+            return Err("expected at least one issuer".to_owned());
+        }
+        Ok(chain)
+    }
+}
+
+impl fmt::Display for IssuerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for IssuerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|k| k.as_str()).collect();
+                format!(
+                    "Unsupported issuer '{}' (supported: {})",
+                    s.trim(),
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// When an untrusted fallback issuer may replace the deployed certificate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FallbackPolicy {
+    /// Never deploy a fallback certificate.
+    Forbid,
+    /// Deploy a fallback certificate when the trusted issuers fail and the
+    /// deployed certificate cannot be used any more. A valid one that is due
+    /// for renewal or outdated is kept instead.
+    #[default]
+    Allow,
+    /// Deploy a fallback certificate only when none is deployed yet, so a
+    /// service can start while the CA is unreachable.
+    AllowIfMissing,
+}
+
+impl FallbackPolicy {
+    /// All policies.
+    pub const ALL: [Self; 3] = [Self::Forbid, Self::Allow, Self::AllowIfMissing];
+
+    /// Canonical configuration name, e.g. `allow-if-missing`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Forbid => "forbid",
+            Self::Allow => "allow",
+            Self::AllowIfMissing => "allow-if-missing",
+        }
+    }
+
+    /// Whether a fallback certificate may replace one in `status`.
+    ///
+    /// A valid certificate is never replaced by a fallback, whether it is a
+    /// trusted one that is due or a fallback itself; only a trusted issuer is
+    /// asked then.
+    #[must_use]
+    pub const fn allows(self, status: &CertStatus) -> bool {
+        match self {
+            Self::Forbid => false,
+            Self::Allow => matches!(
+                status,
+                CertStatus::Missing
+                    | CertStatus::Unreadable { .. }
+                    | CertStatus::KeyMismatch
+                    | CertStatus::Expired { .. }
+            ),
+            Self::AllowIfMissing => matches!(status, CertStatus::Missing),
+        }
+    }
+}
+
+impl fmt::Display for FallbackPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FallbackPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|policy| policy.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|p| p.as_str()).collect();
+                format!(
+                    "Unsupported fallback policy '{}' (supported: {})",
+                    s.trim(),
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// Which issuers of `chain` are fallbacks: the untrusted ones that come
+/// after a trusted one.
+#[must_use]
+pub fn fallbacks(chain: &[Arc<dyn Issuer>]) -> Vec<bool> {
+    let mut trusted_before = false;
+    chain
+        .iter()
+        .map(|issuer| {
+            let fallback = trusted_before && !issuer.is_trusted();
+            trusted_before |= issuer.is_trusted();
+            fallback
+        })
+        .collect()
+}
+
+/// The issuers of [`Config::issuer_chain`], in order.
+///
+/// `step-ca` is left out when no step-ca server is configured, which
+/// configuration validation already reports.
+#[must_use]
+pub fn chain(config: &Config) -> Vec<Arc<dyn Issuer>> {
+    config
+        .issuer_chain()
+        .into_iter()
+        .filter_map(|kind| -> Option<Arc<dyn Issuer>> {
+            match kind {
+                IssuerKind::StepCa => Some(Arc::new(StepCaIssuer::new(config.step_ca.clone()?))),
+                IssuerKind::StepCli => Some(Arc::new(StepCliIssuer)),
+                IssuerKind::SelfSigned => Some(Arc::new(SelfSignedIssuer)),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::step_ca::StepCaConfig;
+
+    #[test]
+    fn test_parse_chain() {
+        assert_eq!(
+            IssuerKind::parse_chain("step-ca, Self-Signed"),
+            Ok(vec![IssuerKind::StepCa, IssuerKind::SelfSigned])
+        );
+        assert_eq!(
+            IssuerKind::parse_chain("step-cli"),
+            Ok(vec![IssuerKind::StepCli])
+        );
+
+        assert!(IssuerKind::parse_chain("").is_err());
+        assert!(IssuerKind::parse_chain("openssl").is_err());
+        assert!(IssuerKind::parse_chain("step-ca,step-ca").is_err());
+    }
+
+    #[test]
+    fn test_fallback_policy() {
+        let missing = CertStatus::Missing;
+        let expired = CertStatus::Expired {
+            not_after: chrono::Utc::now(),
+        };
+        let fallback = CertStatus::Fallback {
+            issuer: "self-signed".to_owned(),
+            remaining: Duration::days(10),
+        };
+        let due = CertStatus::RenewalDue {
+            remaining: Duration::days(3),
+        };
+
+        assert!(FallbackPolicy::Allow.allows(&missing));
+        assert!(FallbackPolicy::Allow.allows(&expired));
+        assert!(!FallbackPolicy::Allow.allows(&fallback));
+        // A trusted certificate that is still valid is kept
+        assert!(!FallbackPolicy::Allow.allows(&due));
+        assert!(FallbackPolicy::AllowIfMissing.allows(&missing));
+        assert!(!FallbackPolicy::AllowIfMissing.allows(&expired));
+        assert!(!FallbackPolicy::Forbid.allows(&missing));
+
+        assert_eq!(
+            "Allow-If-Missing".parse(),
+            Ok(FallbackPolicy::AllowIfMissing)
+        );
+        assert!("never".parse::<FallbackPolicy>().is_err());
+    }
+
+    #[test]
+    fn test_untrusted_issuers_after_a_trusted_one_are_fallbacks() {
+        let local: Vec<Arc<dyn Issuer>> = vec![Arc::new(StepCliIssuer), Arc::new(SelfSignedIssuer)];
+        assert_eq!(fallbacks(&local), [false, false]);
+
+        let ca = StepCaConfig {
+            url: "https://ca.example.com:9000".to_owned(),
+            provisioner: None,
+            password_file: None,
+            provisioner_key_file: None,
+            token_file: Some("/run/secrets/token".to_owned()),
+            root_fingerprint: None,
+            root_cert: Some("/certs/root_ca.crt".to_owned()),
+        };
+        let chain: Vec<Arc<dyn Issuer>> =
+            vec![Arc::new(StepCaIssuer::new(ca)), Arc::new(SelfSignedIssuer)];
+        assert_eq!(fallbacks(&chain), [false, true]);
+    }
+
+    #[test]
+    fn test_bundle_is_split_into_leaf_and_chain() {
+        let leaf = "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n";
+        let intermediate = "-----BEGIN CERTIFICATE-----\nBBBB\n-----END CERTIFICATE-----\n";
+
+        let issued = IssuedCert::from_bundle(&format!("{leaf}{intermediate}"), "key".to_owned());
+        assert_eq!(issued.cert_pem, leaf);
+        assert_eq!(issued.chain_pem, intermediate);
+        assert_eq!(issued.full_chain_pem(), format!("{leaf}{intermediate}"));
+
+        let issued = IssuedCert::from_bundle(leaf, "key".to_owned());
+        assert_eq!(issued.cert_pem, leaf);
+        assert!(issued.chain_pem.is_empty());
+    }
+}
@@ -0,0 +1,48 @@
+//! Self-signed certificates generated in-process.
+
+use super::{IssueRequest, IssuedCert, Issuer};
+use crate::{
+    Error, Result,
+    self_signed::{self, SelfSignedRequest},
+};
+use futures::future::BoxFuture;
+use tracing::info;
+
+/// Issues self-signed certificates, which no client trusts by default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SelfSignedIssuer;
+
+impl Issuer for SelfSignedIssuer {
+    fn name(&self) -> &'static str {
+        "self-signed"
+    }
+
+    fn is_trusted(&self) -> bool {
+        false
+    }
+
+    fn issue<'a>(&'a self, request: &'a IssueRequest) -> BoxFuture<'a, Result<IssuedCert>> {
+        let request = SelfSignedRequest {
+            profile: request.profile.clone(),
+            common_name: request.common_name.clone(),
+            subject_alt_names: request.subject_alt_names.clone(),
+            validity: request.validity,
+            key_type: request.key_type,
+        };
+
+        Box::pin(async move {
+            // RSA key generation can take a while, keep it off the async workers
+            let generated = tokio::task::spawn_blocking(move || self_signed::generate(&request))
+                .await
+                .map_err(|e| Error::issuance(self.name(), e))?
+                .map_err(|e| Error::issuance(self.name(), e))?;
+
+            info!("✅ Self-signed certificate generated");
+            Ok(IssuedCert {
+                cert_pem: generated.cert_pem,
+                chain_pem: String::new(),
+                key_pem: generated.key_pem,
+            })
+        })
+    }
+}
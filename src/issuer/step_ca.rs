@@ -0,0 +1,217 @@
+//! Enrollment and renewal with a step-ca server.
+
+use super::{
+    CurrentCert, IssueRequest, IssuedCert, Issuer,
+    step_cli::{failed, read_issued, unavailable},
+};
+use crate::{
+    Error, Result, certificate,
+    step_ca::{Enrollment, StepCaClient, StepCaConfig, StepCaError},
+};
+use futures::future::BoxFuture;
+use tokio::{fs, process::Command};
+use tracing::{debug, info, warn};
+
+/// Issues certificates from a step-ca server.
+///
+/// A deployed certificate the CA issued is renewed with itself over mTLS, so
+/// no provisioner credentials are needed; everything else is enrolled with
+/// the provisioner.
+#[derive(Debug, Clone)]
+pub struct StepCaIssuer {
+    ca: StepCaConfig,
+}
+
+impl StepCaIssuer {
+    /// Issuer for the step-ca server `ca`.
+    #[must_use]
+    pub const fn new(ca: StepCaConfig) -> Self {
+        Self { ca }
+    }
+
+    /// Whether `current` was issued by a CA rather than self-signed.
+    async fn is_ca_issued(current: &CurrentCert) -> bool {
+        fs::read(&current.cert_path).await.is_ok_and(|data| {
+            certificate::parse_certificate_info(&data).is_ok_and(|info| !info.is_self_issued())
+        })
+    }
+
+    /// Renew the deployed certificate, keeping its key.
+    async fn renew(
+        &self,
+        request: &IssueRequest,
+        current: &CurrentCert,
+        root_pem: &[u8],
+        root_path: &str,
+    ) -> Result<IssuedCert> {
+        debug!("Renewing certificate with Step CA {}", self.ca.url);
+
+        let key_pem = fs::read_to_string(&current.key_path)
+            .await
+            .map_err(Error::io(&current.key_path))?;
+
+        if self.ca.uses_builtin_client() {
+            let client = StepCaClient::new(&self.ca.url, root_pem)?;
+            let cert_pem = fs::read(&current.cert_path)
+                .await
+                .map_err(Error::io(&current.cert_path))?;
+            let renewed = client.renew(&cert_pem, key_pem.as_bytes()).await?;
+            return Ok(IssuedCert::from_bundle(&renewed.chain_pem, key_pem));
+        }
+
+        let out_path = request.work_file("new.crt");
+        let args = self
+            .ca
+            .renew_args(&current.cert_path, &current.key_path, root_path, &out_path);
+        run_step(&args).await?;
+        let bundle = fs::read_to_string(&out_path)
+            .await
+            .map_err(Error::io(&out_path));
+        let _ = fs::remove_file(&out_path).await;
+        Ok(IssuedCert::from_bundle(&bundle?, key_pem))
+    }
+
+    /// Enroll a new key with the provisioner credentials.
+    async fn enroll(
+        &self,
+        request: &IssueRequest,
+        root_pem: &[u8],
+        root_path: &str,
+    ) -> Result<IssuedCert> {
+        debug!("Requesting certificate from Step CA {}", self.ca.url);
+
+        let cert_path = request.work_file("new.crt");
+        let key_path = request.work_file("new.key");
+        let template_data_path = request.work_file("profile.json");
+        let enrollment = Enrollment {
+            subject: &request.common_name,
+            subject_alt_names: &request.subject_alt_names,
+            cert_path: &cert_path,
+            key_path: &key_path,
+            validity: request.validity,
+            key_type: request.key_type,
+            profile: &request.profile,
+            template_data_path: &template_data_path,
+        };
+
+        // A one-time token goes in the request body, since `step` only takes
+        // it on its command line, where other local users can read it
+        if self.ca.uses_builtin_client() || self.ca.token_file.is_some() {
+            let client = StepCaClient::new(&self.ca.url, root_pem)?;
+            let issued = self.ca.enroll(&client, &enrollment).await?;
+            return Ok(IssuedCert::from_bundle(&issued.cert_pem, issued.key_pem));
+        }
+
+        let template_data = enrollment.profile.template_data(enrollment.subject);
+        fs::write(&template_data_path, template_data.to_string())
+            .await
+            .map_err(Error::io(&template_data_path))?;
+        let result = run_step(&self.ca.certificate_args(&enrollment, root_path)).await;
+        let _ = fs::remove_file(&template_data_path).await;
+        result?;
+
+        let issued = read_issued(&cert_path, &key_path).await;
+        let _ = fs::remove_file(&cert_path).await;
+        let _ = fs::remove_file(&key_path).await;
+        issued
+    }
+
+    /// Make sure the CA root certificate is present and matches the configured
+    /// fingerprint, and return it.
+    async fn ensure_root(&self, root_path: &str) -> Result<Vec<u8>> {
+        if fs::metadata(root_path).await.is_err() {
+            let Some(fingerprint) = self.ca.root_fingerprint.as_deref() else {
+                return Err(Error::io(root_path)(std::io::ErrorKind::NotFound.into()));
+            };
+
+            info!("Downloading Step CA root certificate to {root_path}");
+            if self.ca.uses_builtin_client() {
+                let root_pem = StepCaClient::fetch_root(&self.ca.url, fingerprint).await?;
+                fs::write(root_path, root_pem)
+                    .await
+                    .map_err(Error::io(root_path))?;
+            } else if let Some(args) = self.ca.root_args(root_path) {
+                run_step(&args).await?;
+            }
+        }
+
+        let root_pem = fs::read(root_path).await.map_err(Error::io(root_path))?;
+        if let Some(expected) = &self.ca.root_fingerprint {
+            let root = certificate::parse_certificate_info(&root_pem)?;
+            if root.fingerprint_sha256 != *expected {
+                return Err(StepCaError::RootMismatch {
+                    expected: expected.clone(),
+                    actual: root.fingerprint_sha256,
+                }
+                .into());
+            }
+        }
+
+        Ok(root_pem)
+    }
+}
+
+impl Issuer for StepCaIssuer {
+    fn name(&self) -> &'static str {
+        "step-ca"
+    }
+
+    fn issue<'a>(&'a self, request: &'a IssueRequest) -> BoxFuture<'a, Result<IssuedCert>> {
+        Box::pin(async move {
+            let root_path = self.ca.root_path(&request.cert_dir);
+            let root_pem = self.ensure_root(&root_path).await?;
+
+            if let Some(current) = &request.current
+                && Self::is_ca_issued(current).await
+            {
+                match self.renew(request, current, &root_pem, &root_path).await {
+                    Ok(issued) => {
+                        info!("✅ Certificate renewed by Step CA {}", self.ca.url);
+                        return Ok(issued);
+                    }
+                    Err(e) => {
+                        warn!(
+                            "Step CA renewal failed, enrolling with provisioner credentials: {e}"
+                        );
+                    }
+                }
+            }
+
+            let issued = self.enroll(request, &root_pem, &root_path).await?;
+            info!("✅ Certificate issued by Step CA {}", self.ca.url);
+            Ok(issued)
+        })
+    }
+}
+
+/// Run the `step` binary, turning a failure into an error carrying its stderr.
+///
+/// Only the subcommand is named in the error, which reaches logs and
+/// notifications, not the paths and names of the arguments.
+async fn run_step(args: &[String]) -> Result<()> {
+    let output = Command::new("step")
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| unavailable("step-ca", &e))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(failed(
+            "step-ca",
+            format!("step {} failed: {}", subcommand(args), stderr.trim()),
+        ))
+    }
+}
+
+/// The subcommand of `args`, e.g. `ca certificate`.
+fn subcommand(args: &[String]) -> String {
+    args.iter()
+        .take_while(|arg| !arg.starts_with('-'))
+        .take(2)
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,157 @@
+//! Local leaf certificates from `step certificate create`.
+
+use super::{IssueRequest, IssuedCert, Issuer};
+use crate::{Error, Result, duration};
+use futures::future::BoxFuture;
+use tokio::{fs, process::Command};
+use tracing::{debug, info};
+
+/// Issues certificates with the `step` binary, without a CA.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StepCliIssuer;
+
+impl StepCliIssuer {
+    async fn create(self, request: &IssueRequest) -> Result<IssuedCert> {
+        debug!("Generating certificate with Step CLI");
+
+        // The template carries the subject, SANs and the profile's extensions
+        let template_path = request.work_file("profile.json");
+        let template = request
+            .profile
+            .step_template(&request.common_name, &request.subject_alt_names);
+        fs::write(&template_path, template)
+            .await
+            .map_err(Error::io(&template_path))?;
+
+        let cert_path = request.work_file("new.crt");
+        let key_path = request.work_file("new.key");
+        let _ = fs::remove_file(&cert_path).await;
+        let _ = fs::remove_file(&key_path).await;
+
+        let mut cmd = Command::new("step");
+        cmd.args([
+            "certificate",
+            "create",
+            &request.common_name,
+            &cert_path,
+            &key_path,
+            "--template",
+            &template_path,
+            "--not-after",
+            &duration::format_go(request.validity),
+        ]);
+        cmd.args(request.key_type.step_args());
+
+        let output = cmd.output().await;
+        let _ = fs::remove_file(&template_path).await;
+        let output = output.map_err(|e| unavailable(self.name(), &e))?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(failed(self.name(), stderr.trim().to_owned()));
+        }
+
+        let issued = read_issued(&cert_path, &key_path).await;
+        let _ = fs::remove_file(&cert_path).await;
+        let _ = fs::remove_file(&key_path).await;
+        info!("✅ Certificate generated successfully with Step CLI");
+        issued
+    }
+}
+
+impl Issuer for StepCliIssuer {
+    fn name(&self) -> &'static str {
+        "step-cli"
+    }
+
+    fn is_trusted(&self) -> bool {
+        false
+    }
+
+    fn issue<'a>(&'a self, request: &'a IssueRequest) -> BoxFuture<'a, Result<IssuedCert>> {
+        Box::pin(self.create(request))
+    }
+}
+
+/// Phrases of `step` errors saying the CA or a key rejected the credentials,
+/// which trying again does not change.
+const REJECTIONS: [&str; 6] = [
+    "unauthorized",
+    "forbidden",
+    "lacked necessary authorization",
+    "password",
+    "decrypt",
+    "certificate signed by unknown authority",
+];
+
+/// The error of the `step` binary failing to start for `backend`.
+///
+/// A binary that is missing or may not be run needs someone to install it;
+/// anything else may pass.
+pub(crate) fn unavailable(backend: &str, error: &std::io::Error) -> Error {
+    Error::Issuance {
+        backend: backend.to_owned(),
+        source: format!("Step CLI unavailable: {error}").into(),
+        retryable: !matches!(
+            error.kind(),
+            std::io::ErrorKind::NotFound | std::io::ErrorKind::PermissionDenied
+        ),
+    }
+}
+
+/// The error of the `step` binary failing for `backend` with `message`,
+/// which ends with its stderr.
+///
+/// Rejected credentials stay rejected; anything else may be a CA that is
+/// unreachable for now.
+pub(crate) fn failed(backend: &str, message: String) -> Error {
+    let lowercase = message.to_lowercase();
+    let rejected = REJECTIONS.iter().any(|phrase| lowercase.contains(phrase));
+    Error::Issuance {
+        backend: backend.to_owned(),
+        source: message.into(),
+        retryable: !rejected,
+    }
+}
+
+/// Read the certificate bundle and key `step` wrote.
+pub(crate) async fn read_issued(cert_path: &str, key_path: &str) -> Result<IssuedCert> {
+    let bundle = fs::read_to_string(cert_path)
+        .await
+        .map_err(Error::io(cert_path))?;
+    let key_pem = fs::read_to_string(key_path)
+        .await
+        .map_err(Error::io(key_path))?;
+    Ok(IssuedCert::from_bundle(&bundle, key_pem))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step_failures() {
+        let missing = std::io::Error::from(std::io::ErrorKind::NotFound);
+        assert!(!unavailable("step-ca", &missing).is_retryable());
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(!unavailable("step-ca", &denied).is_retryable());
+        let interrupted = std::io::Error::from(std::io::ErrorKind::Interrupted);
+        assert!(unavailable("step-ca", &interrupted).is_retryable());
+
+        let rejected = failed(
+            "step-ca",
+            "step ca certificate failed: The request lacked necessary authorization to be completed."
+                .to_owned(),
+        );
+        assert!(!rejected.is_retryable());
+        assert!(!failed("step-ca", "error decrypting provisioner key".to_owned()).is_retryable());
+        let unreachable = failed(
+            "step-ca",
+            "step ca certificate failed: connection refused".to_owned(),
+        );
+        assert!(unreachable.is_retryable());
+        assert_eq!(
+            unreachable.to_string(),
+            "step-ca: step ca certificate failed: connection refused"
+        );
+    }
+}
@@ -1,15 +1,22 @@
 //! Simple certificate manager - Rust port of cert-manager.sh
 //!
-//! Automated certificate lifecycle management using Step CLI or OpenSSL.
+//! Automated certificate lifecycle management using Step CLI with a self-signed fallback.
 
-use dimension_bridge::{CertManager, Config};
+use dimension_bridge::{CertManager, CertStatus, Config, Error, Settings, settings};
 use std::env;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command line arguments first
-    let args: Vec<String> = env::args().collect();
-    let command = args.get(1).map(String::as_str);
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{e}");
+            show_help();
+            std::process::exit(1);
+        }
+    };
+    let command = cli.args.first().map(String::as_str);
 
     // Handle commands that don't need configuration
     match command {
@@ -18,9 +25,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             println!("Rust port of cert-manager.sh");
             println!();
             println!("Features:");
+            println!("  - Step CA enrollment");
             println!("  - Step CLI integration");
-            println!("  - OpenSSL fallback");
+            println!("  - Self-signed fallback (no openssl required)");
             println!("  - Automatic renewal");
+            println!("  - Many certificates from one agent");
             println!("  - Slack notifications");
             println!("  - Docker-friendly");
             return Ok(());
@@ -29,7 +38,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             show_help();
             return Ok(());
         }
-        Some(unknown) if unknown.starts_with('-') || unknown == "invalid-command" => {
+        Some("config") => {
+            if cli.args.get(1).map(String::as_str) != Some("validate") {
+                eprintln!("Unknown command: {}", cli.args.join(" "));
+                show_help();
+                std::process::exit(1);
+            }
+            validate_config(&cli);
+            return Ok(());
+        }
+        Some(unknown) if unknown == "invalid-command" => {
             eprintln!("Unknown command: {unknown}");
             show_help();
             std::process::exit(1);
@@ -42,7 +60,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tracing_subscriber::fmt().with_env_filter(log_level).init();
 
     // Load configuration (required for all other commands)
-    let config = Config::from_env()?;
+    let config = load_config(&cli);
     let manager = CertManager::new(config);
 
     // Initialize
@@ -51,7 +69,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Handle commands that need configuration
     match command {
         Some("once") => {
-            manager.run_once().await?;
+            let results = manager.run_once_all().await;
+            let single = results.len() == 1;
+
+            let mut statuses = Vec::new();
+            let mut failed = false;
+            for (name, result) in results {
+                match result {
+                    Ok(status) => statuses.push((name, status)),
+                    Err(e) if single => return Err(e.into()),
+                    Err(e) => {
+                        eprintln!("{name}: {e}");
+                        failed = true;
+                    }
+                }
+            }
+
+            if !single {
+                report_statuses(&statuses);
+            }
+            if failed {
+                std::process::exit(1);
+            }
+            exit_with_first_failure(&statuses);
+        }
+        Some("health") => {
+            let statuses = manager.check_all().await;
+            report_statuses(&statuses);
+            exit_with_first_failure(&statuses);
         }
         Some(command) => {
             eprintln!("Unknown command: {command}");
@@ -60,34 +105,214 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         None => {
             // Default: run daemon
-            manager.run().await?;
+            manager.run().await;
         }
     }
 
     Ok(())
 }
 
+/// Parsed command line.
+struct Cli {
+    /// Command words, e.g. `["config", "validate"]`.
+    args: Vec<String>,
+    /// Configuration file given with `--config`.
+    config_file: Option<String>,
+    /// Settings given as `--setting value` or `--setting=value`.
+    overrides: Vec<(String, String)>,
+}
+
+impl Cli {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut cli = Self {
+            args: Vec::new(),
+            config_file: None,
+            overrides: Vec::new(),
+        };
+
+        while let Some(arg) = args.next() {
+            let Some(flag) = arg.strip_prefix("--") else {
+                if arg.starts_with('-') {
+                    return Err(format!("Unknown command: {arg}"));
+                }
+                cli.args.push(arg);
+                continue;
+            };
+
+            let (flag, value) = match flag.split_once('=') {
+                Some((flag, value)) => (flag, value.to_owned()),
+                None => (
+                    flag,
+                    args.next()
+                        .ok_or_else(|| format!("Option --{flag} requires a value"))?,
+                ),
+            };
+
+            let name = settings::setting_name(flag);
+            if flag == "config" {
+                cli.config_file = Some(value);
+            } else if settings::KNOWN_SETTINGS.contains(&name.as_str()) {
+                cli.overrides.push((name, value));
+            } else {
+                return Err(format!("Unknown option: --{flag}"));
+            }
+        }
+
+        Ok(cli)
+    }
+}
+
+/// Load the configuration, or print every problem with it and exit.
+fn load_config(cli: &Cli) -> Config {
+    let config = Settings::load(cli.config_file.as_deref(), &cli.overrides)
+        .map_err(Error::from)
+        .and_then(|settings| Config::from_settings(&settings));
+    config.unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// Print the effective configuration, or every problem with it and exit.
+fn validate_config(cli: &Cli) {
+    print!("{}", settings::to_toml(&load_config(cli)));
+}
+
+/// Exit with the code assigned to a certificate status, if it is non-zero.
+fn exit_with_status(status: &CertStatus) {
+    let code = status.exit_code();
+    if code != 0 {
+        std::process::exit(code);
+    }
+}
+
+/// Print each status, prefixed by the certificate name when there are several.
+fn report_statuses(statuses: &[(String, CertStatus)]) {
+    if let [(_, status)] = statuses {
+        println!("{status}");
+    } else {
+        for (name, status) in statuses {
+            println!("{name}: {status}");
+        }
+    }
+}
+
+/// Exit with the code of the first certificate that is not healthy, if any.
+fn exit_with_first_failure(statuses: &[(String, CertStatus)]) {
+    if let Some((_, status)) = statuses.iter().find(|(_, status)| status.exit_code() != 0) {
+        exit_with_status(status);
+    }
+}
+
 fn show_help() {
     println!("Simple Certificate Manager v{}", env!("CARGO_PKG_VERSION"));
     println!("Automated certificate lifecycle management");
     println!();
     println!("USAGE:");
-    println!("    dimension-bridge [COMMAND]");
+    println!("    dimension-bridge [OPTIONS] [COMMAND]");
     println!();
     println!("COMMANDS:");
     println!("    <none>      Run continuously (daemon mode)");
     println!("    once        Run once and exit");
+    println!("    health      Report certificate status and exit");
+    println!("    config validate");
+    println!("                Check the configuration and print the effective settings");
     println!("    version     Show version information");
     println!("    help        Show this help message");
     println!();
+    println!("OPTIONS:");
+    println!(
+        "    --config PATH         TOML configuration file (default: $DIMENSION_BRIDGE_CONFIG)"
+    );
+    println!("    --SETTING VALUE       Override a setting below, e.g. --cert-dir /certs");
+    println!();
     println!("ENVIRONMENT VARIABLES:");
-    println!("    SERVER_IP             Server IP for certificate SAN (required)");
+    println!("    Settings are read from the configuration file, then the environment,");
+    println!("    then the command line. File keys are the names below in lower case,");
+    println!("    e.g. cert_dir, [step_ca] url, and [[certificates]] with a name.");
+    println!();
+    println!("    SERVER_IP             Server IP or name, used as common name and SAN");
+    println!("    CERT_DOMAINS          Comma-separated SANs: names, wildcards, IPv4/IPv6,");
+    println!("                          URI: and email: entries");
+    println!("                          (SERVER_IP or CERT_DOMAINS is required)");
+    println!("    EXTRA_SANS            SANs added to every certificate, or 'none'");
+    println!("                          (default: localhost,127.0.0.1)");
     println!("    SERVICE_NAME          Service name for certificate files (default: cert-agent)");
     println!("    CERT_DIR              Certificate directory (default: /certs)");
-    println!("    CHECK_INTERVAL        Check interval in seconds (default: 86400)");
-    println!("    DAYS_BEFORE_RENEWAL   Days before expiry to renew (default: 5)");
-    println!("    CERT_VALIDITY_DAYS    Certificate validity in days (default: 15)");
+    println!("    CHECK_INTERVAL        Time between checks, e.g. 6h or 90m; a bare number");
+    println!("                          is seconds (default: 1d)");
+    println!("    DAYS_BEFORE_RENEWAL   Time before expiry to renew, e.g. 8h; a bare number");
+    println!("                          is days (default: 5d)");
+    println!("    RENEW_AT_PERCENT      Renew once this % of the certificate lifetime has");
+    println!("                          elapsed, instead of DAYS_BEFORE_RENEWAL (1-99)");
+    println!("    CERT_VALIDITY_DAYS    Certificate validity, e.g. 24h; a bare number is days");
+    println!("                          (default: 15d)");
+    println!("    EXPIRY_WARNINGS       Warn once when this much time is left before expiry,");
+    println!("                          e.g. 14,7,3,1; a bare number is days (default: none)");
+    println!("    DEDUP_WINDOWS         Hold back repeated notifications as event=duration,");
+    println!("                          e.g. failed=1h (default: 6h for clock-skew,");
+    println!("                          backup-failed and failed)");
+    println!("    NOTIFY_RATE_LIMIT     Most notifications per period, e.g. 20/1h");
+    println!("    ESCALATE_AFTER        Failed checks in a row before alerting escalation sinks");
+    println!("    KEY_TYPE              Key algorithm: ecdsa-p256, ecdsa-p384, ed25519,");
+    println!("                          rsa-2048, rsa-3072, rsa-4096 (default: that of the");
+    println!("                          deployed certificate, else ecdsa-p256)");
+    println!("    ISSUERS               Issuers to try in order: step-ca, step-cli, self-signed");
+    println!("                          (default: step-ca or step-cli, then self-signed)");
+    println!("    FALLBACK_POLICY       When self-signed may stand in for a failing CA: forbid,");
+    println!("                          allow, allow-if-missing (default: allow)");
+    println!("    CERT_PROFILE          Certificate profile: server, client, server-client");
+    println!("                          (default: server)");
+    println!("    CERT_SUBJECT          Subject fields as C=..,ST=..,L=..,O=..,OU=..");
+    println!("                          (default: C=KR,O=SERVICE_NAME Service)");
+    println!("    CERT_POLICY_OIDS      Comma-separated certificate policy OIDs");
+    println!("    STEP_CA_URL           Step CA URL to enroll with (optional)");
+    println!("    STEP_CA_FINGERPRINT   Step CA root certificate fingerprint");
+    println!(
+        "    STEP_CA_ROOT          Step CA root certificate path (default: CERT_DIR/root_ca.crt)"
+    );
+    println!("    STEP_CA_PROVISIONER   Step CA provisioner name");
+    println!("    STEP_CA_PROVISIONER_PASSWORD_FILE");
+    println!("                          File with the provisioner (key) password");
+    println!("    STEP_CA_PROVISIONER_KEY_FILE");
+    println!("                          JWK provisioner key, enables the built-in client");
+    println!("    STEP_CA_TOKEN_FILE    File with a one-time enrollment token");
     println!("    RELOAD_COMMAND        Command to reload service (optional)");
-    println!("    SLACK_WEBHOOK_URL     Slack webhook for notifications (optional)");
+    println!("    CERTIFICATES          Comma-separated certificate names to manage instead of");
+    println!("                          SERVICE_NAME; each NAME reads NAME_SERVER_IP,");
+    println!(
+        "                          NAME_CERT_DOMAINS, NAME_CERT_DIR, NAME_CERT_VALIDITY_DAYS,"
+    );
+    println!("                          NAME_DAYS_BEFORE_RENEWAL, NAME_RENEW_AT_PERCENT,");
+    println!("                          NAME_RELOAD_COMMAND and");
+    println!("                          NAME_CERT_PROFILE/SUBJECT/POLICY_OIDS, falling back to");
+    println!("                          the settings above");
+    println!("    MAX_PARALLEL_RENEWALS Certificates renewed at the same time (default: 4)");
+    println!("    SLACK_WEBHOOK_URL     Slack webhook for all notifications (optional)");
+    println!("    NOTIFIERS             Comma-separated notification sinks; each NAME reads");
+    println!("                          NOTIFIER_NAME_TYPE (slack, webhook, email),");
+    println!("                          NOTIFIER_NAME_URL");
+    println!("                          and NOTIFIER_NAME_MIN_SEVERITY (info, warning, error;");
+    println!("                          default: info), NOTIFIER_NAME_ESCALATION (true to");
+    println!("                          receive escalations only); webhooks also read");
+    println!("                          NOTIFIER_NAME_TEMPLATE, NOTIFIER_NAME_HEADERS,");
+    println!("                          NOTIFIER_NAME_SECRET_FILE and");
+    println!("                          NOTIFIER_NAME_SIGNATURE_HEADER; email reads");
+    println!("                          NOTIFIER_NAME_FROM, NOTIFIER_NAME_TO,");
+    println!("                          NOTIFIER_NAME_TLS (starttls, tls, none),");
+    println!("                          NOTIFIER_NAME_USERNAME and");
+    println!("                          NOTIFIER_NAME_PASSWORD_FILE");
     println!("    RUST_LOG              Log level (default: info)");
+    println!();
+    println!("EXIT CODES (once, health):");
+    println!("    0  Certificate healthy");
+    println!("    1  Runtime or configuration error");
+    println!("    2  Renewal due");
+    println!("    3  Certificate expired");
+    println!("    4  Certificate not valid yet");
+    println!("    5  Certificate missing");
+    println!("    6  Certificate or key unreadable");
+    println!("    7  Private key does not match certificate");
+    println!("    8  Certificate no longer matches configuration");
+    println!("    9  Fallback certificate deployed, CA unreachable");
 }
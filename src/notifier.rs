@@ -0,0 +1,622 @@
+//! Notification sinks.
+//!
+//! A [`Notifier`] delivers a [`Notification`] to one destination. Every
+//! configured sink receives the notifications at or above its minimum
+//! [`Severity`], so renewals can go to a team channel while only failures
+//! page the on-call webhook. A destination outside this crate can be added by
+//! implementing the trait and putting it in [`CertManager::notifiers`].
+//!
+//! Escalation sinks only receive the alerts escalated after a number of
+//! failed checks in a row, see [`throttle`] for how the daemon holds back
+//! repeats.
+//!
+//! [`CertManager::notifiers`]: crate::CertManager::notifiers
+
+pub mod email;
+pub mod slack;
+pub mod throttle;
+pub mod webhook;
+
+pub use email::{EmailNotifier, EmailSpec, SmtpTls};
+pub use slack::SlackNotifier;
+pub use throttle::RateLimit;
+pub use webhook::{WebhookNotifier, WebhookSpec};
+
+use crate::{
+    CertificateInfo, Config, Error, Result,
+    settings::{Settings, Validation, env_prefix},
+};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use std::{fmt, str::FromStr, sync::Arc};
+
+/// A destination for notifications.
+pub trait Notifier: Send + Sync {
+    /// Name used in logs and errors, e.g. `oncall`.
+    fn name(&self) -> &str;
+
+    /// Deliver `notification`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Notification`] if the destination cannot be reached
+    /// or rejects the notification.
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>>;
+}
+
+/// How urgent a notification is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Severity {
+    /// Something went as planned, e.g. a renewal.
+    #[default]
+    Info,
+    /// Something needs attention soon, e.g. a fallback certificate.
+    Warning,
+    /// Something failed and needs someone to act.
+    Error,
+}
+
+impl Severity {
+    /// All severities, from least to most urgent.
+    pub const ALL: [Self; 3] = [Self::Info, Self::Warning, Self::Error];
+
+    /// Canonical configuration name, e.g. `warning`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|severity| severity.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|s| s.as_str()).collect();
+                format!(
+                    "Unsupported severity '{}' (supported: {})",
+                    s.trim(),
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// What a notification is about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Event {
+    /// A new certificate was deployed.
+    Renewed,
+    /// The deployed certificate crossed an expiry warning threshold.
+    Expiring,
+    /// A fallback certificate was deployed because the CA failed.
+    Fallback,
+    /// The deployed certificate is not valid yet.
+    ClockSkew,
+    /// The certificate could not be backed up before renewal.
+    BackupFailed,
+    /// A check or renewal failed and needs someone to act.
+    Failed,
+    /// Checks succeed again after failures that were alerted on.
+    Recovered,
+}
+
+impl Event {
+    /// All events.
+    pub const ALL: [Self; 7] = [
+        Self::Renewed,
+        Self::Expiring,
+        Self::Fallback,
+        Self::ClockSkew,
+        Self::BackupFailed,
+        Self::Failed,
+        Self::Recovered,
+    ];
+
+    /// Name used in notifications, e.g. `backup-failed`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Renewed => "renewed",
+            Self::Expiring => "expiring",
+            Self::Fallback => "fallback",
+            Self::ClockSkew => "clock-skew",
+            Self::BackupFailed => "backup-failed",
+            Self::Failed => "failed",
+            Self::Recovered => "recovered",
+        }
+    }
+
+    /// How urgent notifications about the event are.
+    #[must_use]
+    pub const fn severity(self) -> Severity {
+        match self {
+            Self::Renewed | Self::Recovered => Severity::Info,
+            Self::Expiring | Self::Fallback | Self::ClockSkew => Severity::Warning,
+            Self::BackupFailed | Self::Failed => Severity::Error,
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Event {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|event| event.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|e| e.as_str()).collect();
+                format!(
+                    "Unsupported event '{}' (supported: {})",
+                    s.trim(),
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// Something worth telling about a managed certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    /// Name of the certificate, e.g. `api-gateway`.
+    pub service: String,
+    /// What happened.
+    pub event: Event,
+    /// How urgent it is.
+    pub severity: Severity,
+    /// What happened, for people.
+    pub message: String,
+    /// The deployed certificate, when it can be read.
+    pub certificate: Option<CertificateInfo>,
+    /// The certificate it replaced, for renewals.
+    pub previous: Option<CertificateInfo>,
+    /// Issuer of the deployed certificate, e.g. `step-ca`, for renewals.
+    pub issuer: Option<String>,
+    /// The failure, for failure events.
+    pub error: Option<String>,
+    /// When the failed work is tried again, for failure events.
+    pub next_retry: Option<DateTime<Utc>>,
+    /// Whether escalation sinks receive it too.
+    pub escalated: bool,
+    /// When it happened.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Notification {
+    /// Notification about `event` of certificate `service`, at the severity
+    /// of the event.
+    #[must_use]
+    pub fn new(service: &str, event: Event, message: &str) -> Self {
+        Self {
+            service: service.to_owned(),
+            event,
+            severity: event.severity(),
+            message: message.to_owned(),
+            certificate: None,
+            previous: None,
+            issuer: None,
+            error: None,
+            next_retry: None,
+            escalated: false,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Whole days until the deployed certificate expires.
+    #[must_use]
+    pub fn days_remaining(&self) -> Option<i64> {
+        self.certificate
+            .as_ref()
+            .map(|info| info.validity.remaining(self.timestamp).num_days())
+    }
+}
+
+/// A notifier and the least severe notifications it receives.
+#[derive(Clone)]
+pub struct Sink {
+    /// Where notifications go.
+    pub notifier: Arc<dyn Notifier>,
+    /// Less severe notifications are not sent to `notifier`.
+    pub min_severity: Severity,
+    /// Only escalated notifications are sent to `notifier`.
+    pub escalation: bool,
+}
+
+impl Sink {
+    /// Sink sending notifications at or above `min_severity` to `notifier`.
+    #[must_use]
+    pub fn new(notifier: Arc<dyn Notifier>, min_severity: Severity) -> Self {
+        Self {
+            notifier,
+            min_severity,
+            escalation: false,
+        }
+    }
+
+    /// Sink sending only escalated notifications at or above `min_severity`
+    /// to `notifier`.
+    #[must_use]
+    pub fn escalation(notifier: Arc<dyn Notifier>, min_severity: Severity) -> Self {
+        Self {
+            escalation: true,
+            ..Self::new(notifier, min_severity)
+        }
+    }
+
+    /// Whether notifications of `severity` are sent to this sink.
+    #[must_use]
+    pub fn accepts(&self, severity: Severity) -> bool {
+        severity >= self.min_severity
+    }
+
+    /// Whether `notification` is sent to this sink.
+    ///
+    /// A recovery is judged at the severity of the failures it ends, so it
+    /// reaches the sinks that were alerted about them.
+    #[must_use]
+    pub fn receives(&self, notification: &Notification) -> bool {
+        let severity = match notification.event {
+            Event::Recovered => notification.severity.max(Event::Failed.severity()),
+            _ => notification.severity,
+        };
+        self.accepts(severity) && (notification.escalated || !self.escalation)
+    }
+}
+
+/// Notifiers built into this crate, as named in `NOTIFIER_{NAME}_TYPE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NotifierKind {
+    /// A Slack incoming webhook.
+    Slack,
+    /// A JSON document posted to any URL.
+    Webhook,
+    /// An email sent through an SMTP server.
+    Email,
+}
+
+impl NotifierKind {
+    /// All built-in notifiers.
+    pub const ALL: [Self; 3] = [Self::Slack, Self::Webhook, Self::Email];
+
+    /// Canonical configuration name, e.g. `webhook`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Slack => "slack",
+            Self::Webhook => "webhook",
+            Self::Email => "email",
+        }
+    }
+}
+
+impl fmt::Display for NotifierKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for NotifierKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|k| k.as_str()).collect();
+                format!(
+                    "Unsupported notifier type '{}' (supported: {})",
+                    s.trim(),
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// A configured notification sink.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotifierSpec {
+    /// Sink name, used in logs and errors.
+    pub name: String,
+    /// URL notifications are posted to, or the `smtp://` server emails are
+    /// sent through.
+    pub url: String,
+    /// Less severe notifications are not sent.
+    pub min_severity: Severity,
+    /// Only escalated notifications are sent.
+    pub escalation: bool,
+    /// Kind of destination, with the settings only that kind reads.
+    pub settings: NotifierSettings,
+}
+
+/// Kind of a notification sink, with its own settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifierSettings {
+    /// A Slack incoming webhook.
+    Slack,
+    /// A generic JSON webhook.
+    Webhook(WebhookSpec),
+    /// Email through an SMTP server.
+    Email(EmailSpec),
+}
+
+impl NotifierSettings {
+    /// Kind of destination.
+    #[must_use]
+    pub const fn kind(&self) -> NotifierKind {
+        match self {
+            Self::Slack => NotifierKind::Slack,
+            Self::Webhook(_) => NotifierKind::Webhook,
+            Self::Email(_) => NotifierKind::Email,
+        }
+    }
+}
+
+impl NotifierSpec {
+    /// Name of the sink configured by `SLACK_WEBHOOK_URL`.
+    pub const SLACK_WEBHOOK: &str = "slack";
+
+    /// Load every sink: the one of `SLACK_WEBHOOK_URL`, then those listed in
+    /// `NOTIFIERS`, recording problems in `validation`.
+    pub(crate) fn load_all(settings: &Settings, validation: &mut Validation) -> Vec<Self> {
+        let mut specs = Vec::new();
+        if let Some(url) = settings.get("SLACK_WEBHOOK_URL") {
+            specs.push(Self {
+                name: Self::SLACK_WEBHOOK.to_owned(),
+                url: url.to_owned(),
+                min_severity: Severity::Info,
+                escalation: false,
+                settings: NotifierSettings::Slack,
+            });
+        }
+
+        let names = settings.get("NOTIFIERS").unwrap_or_default().split(',');
+        for name in names.map(str::trim).filter(|name| !name.is_empty()) {
+            if specs.iter().any(|spec: &Self| spec.name == name) {
+                validation.error(format!("Notifier '{name}' is defined twice"));
+                continue;
+            }
+            if let Some(spec) = Self::load(settings, name, validation) {
+                specs.push(spec);
+            }
+        }
+        specs
+    }
+
+    /// Load sink `name` from its `NOTIFIER_{NAME}_*` settings.
+    fn load(settings: &Settings, name: &str, validation: &mut Validation) -> Option<Self> {
+        let prefix = prefix(name);
+        let kind_setting = format!("{prefix}TYPE");
+        let url_setting = format!("{prefix}URL");
+
+        let kind = validation.parse_with(settings, &kind_setting, str::parse::<NotifierKind>);
+        let min_severity =
+            validation.parse(settings, &format!("{prefix}MIN_SEVERITY"), Severity::Info);
+        let escalation = validation.parse(settings, &format!("{prefix}ESCALATION"), false);
+        let url = settings
+            .get(&url_setting)
+            .filter(|url| !url.trim().is_empty());
+
+        if settings.get(&kind_setting).is_none() {
+            validation.error(format!("{kind_setting} is required for notifier '{name}'"));
+        }
+        if url.is_none() {
+            validation.error(format!("{url_setting} is required for notifier '{name}'"));
+        }
+
+        let kind = kind?;
+        for (other, keys) in [
+            (NotifierKind::Webhook, WebhookSpec::SETTINGS.as_slice()),
+            (NotifierKind::Email, EmailSpec::SETTINGS.as_slice()),
+        ] {
+            if kind == other {
+                continue;
+            }
+            for setting in keys.iter().map(|key| format!("{prefix}{key}")) {
+                if let Some(value) = settings.get(&setting) {
+                    validation.invalid(
+                        &setting,
+                        value,
+                        format_args!("only supported by {other} notifiers"),
+                    );
+                }
+            }
+        }
+        let kind_settings = match kind {
+            NotifierKind::Slack => NotifierSettings::Slack,
+            NotifierKind::Webhook => {
+                NotifierSettings::Webhook(WebhookSpec::load(settings, &prefix, validation))
+            }
+            NotifierKind::Email => {
+                NotifierSettings::Email(EmailSpec::load(settings, name, &prefix, url, validation)?)
+            }
+        };
+
+        Some(Self {
+            name: name.to_owned(),
+            url: url?.trim().to_owned(),
+            min_severity,
+            escalation,
+            settings: kind_settings,
+        })
+    }
+
+    /// Kind of destination.
+    #[must_use]
+    pub const fn kind(&self) -> NotifierKind {
+        self.settings.kind()
+    }
+}
+
+/// Prefix of the settings of notifier `name`, e.g. `NOTIFIER_ONCALL_`.
+#[must_use]
+pub fn prefix(name: &str) -> String {
+    format!("NOTIFIER_{}", env_prefix(name))
+}
+
+/// The sinks of [`Config::notifiers`], posting with `client`.
+#[must_use]
+pub fn sinks(config: &Config, client: &reqwest::Client) -> Vec<Sink> {
+    config
+        .notifiers
+        .iter()
+        .map(|spec| {
+            let notifier: Arc<dyn Notifier> = match &spec.settings {
+                NotifierSettings::Slack => {
+                    Arc::new(SlackNotifier::new(&spec.name, &spec.url, client.clone()))
+                }
+                NotifierSettings::Webhook(settings) => {
+                    let mut webhook = WebhookNotifier::new(&spec.name, &spec.url, client.clone())
+                        .with_headers(settings.headers.clone());
+                    if let Some(template) = &settings.template {
+                        webhook = webhook.with_template(template.clone());
+                    }
+                    if let Some(secret_file) = &settings.secret_file {
+                        webhook = webhook.signed(secret_file, &settings.signature_header);
+                    }
+                    Arc::new(webhook)
+                }
+                NotifierSettings::Email(settings) => {
+                    Arc::new(EmailNotifier::new(&spec.name, settings.clone()))
+                }
+            };
+            Sink {
+                escalation: spec.escalation,
+                ..Sink::new(notifier, spec.min_severity)
+            }
+        })
+        .collect()
+}
+
+/// Send `request`, attributing a failure to `channel`.
+async fn send_request(channel: &str, request: reqwest::RequestBuilder) -> Result<()> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| delivery_failed(channel, e))?;
+
+    if !response.status().is_success() {
+        return Err(delivery_failed(channel, response.status()));
+    }
+    Ok(())
+}
+
+/// The error of a notification `channel` failed to deliver.
+fn delivery_failed(channel: &str, reason: impl fmt::Display) -> Error {
+    Error::Notification {
+        channel: channel.to_owned(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_severity_filter() {
+        let client = reqwest::Client::new();
+        let sink = Sink::new(
+            Arc::new(WebhookNotifier::new("oncall", "https://x", client)),
+            Severity::Warning,
+        );
+        assert!(!sink.accepts(Severity::Info));
+        assert!(sink.accepts(Severity::Warning));
+        assert!(sink.accepts(Severity::Error));
+
+        let pager = Sink::escalation(Arc::clone(&sink.notifier), Severity::Warning);
+        let mut notification = Notification::new("api", Event::Failed, "❌ Failed");
+        assert!(sink.receives(&notification));
+        assert!(!pager.receives(&notification));
+        notification.escalated = true;
+        assert!(sink.receives(&notification));
+        assert!(pager.receives(&notification));
+
+        assert_eq!(" Error".parse(), Ok(Severity::Error));
+        assert!("critical".parse::<Severity>().is_err());
+    }
+
+    #[test]
+    fn test_notifiers_from_settings() {
+        let mut settings = Settings::default();
+        settings.set("SLACK_WEBHOOK_URL", "https://hooks.slack.com/services/a");
+        settings.set("NOTIFIERS", "on-call, audit, mail");
+        settings.set("NOTIFIER_ON_CALL_TYPE", "webhook");
+        settings.set("NOTIFIER_ON_CALL_URL", "https://pager.example.com/hook");
+        settings.set("NOTIFIER_ON_CALL_MIN_SEVERITY", "error");
+        settings.set("NOTIFIER_ON_CALL_ESCALATION", "true");
+        settings.set("NOTIFIER_AUDIT_TYPE", "slack");
+        settings.set("NOTIFIER_AUDIT_URL", "https://hooks.slack.com/services/b");
+        settings.set("NOTIFIER_MAIL_TYPE", "email");
+        settings.set("NOTIFIER_MAIL_URL", "smtp://mail.example.com");
+        settings.set("NOTIFIER_MAIL_FROM", "Certificates <certs@example.com>");
+        settings.set("NOTIFIER_MAIL_TO", "ops@example.com, sre@example.com");
+
+        let mut validation = Validation::default();
+        let specs = NotifierSpec::load_all(&settings, &mut validation);
+        assert!(validation.finish().is_ok());
+        let summary: Vec<_> = specs
+            .iter()
+            .map(|spec| (spec.name.as_str(), spec.kind(), spec.min_severity))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("slack", NotifierKind::Slack, Severity::Info),
+                ("on-call", NotifierKind::Webhook, Severity::Error),
+                ("audit", NotifierKind::Slack, Severity::Info),
+                ("mail", NotifierKind::Email, Severity::Info),
+            ]
+        );
+        if let NotifierSettings::Email(email) = &specs[3].settings {
+            assert_eq!(
+                (email.host.as_str(), email.port, email.tls),
+                ("mail.example.com", 587, SmtpTls::StartTls)
+            );
+            assert_eq!(email.to.len(), 2);
+        }
+        let escalation: Vec<_> = specs.iter().map(|spec| spec.escalation).collect();
+        assert_eq!(escalation, [false, true, false, false]);
+
+        settings.set("NOTIFIERS", "slack,pager,audit,mail");
+        settings.set("NOTIFIER_PAGER_TYPE", "sms");
+        settings.set("NOTIFIER_AUDIT_TO", "ops@example.com");
+        settings.set("NOTIFIER_MAIL_USERNAME", "certs");
+        let mut validation = Validation::default();
+        NotifierSpec::load_all(&settings, &mut validation);
+        let errors = validation.finish().unwrap_err().errors;
+        assert_eq!(
+            errors,
+            [
+                "Notifier 'slack' is defined twice",
+                "NOTIFIER_PAGER_TYPE=\"sms\": Unsupported notifier type 'sms' (supported: slack, webhook, email)",
+                "NOTIFIER_PAGER_URL is required for notifier 'pager'",
+                "NOTIFIER_AUDIT_TO=\"ops@example.com\": only supported by email notifiers",
+                "NOTIFIER_MAIL_USERNAME and NOTIFIER_MAIL_PASSWORD_FILE must be set together for notifier 'mail'",
+            ]
+        );
+    }
+}
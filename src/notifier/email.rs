@@ -0,0 +1,448 @@
+//! Email over SMTP.
+//!
+//! Every notification is one message with a plain-text and an HTML body, so
+//! teams without a chat tool get the same renewal events by mail.
+
+use super::{Notification, Notifier, delivery_failed};
+use crate::{
+    Result,
+    settings::{Settings, Validation},
+};
+use futures::future::BoxFuture;
+use lettre::{
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+    message::{Mailbox, MultiPart},
+    transport::smtp::authentication::Credentials,
+};
+use std::{fmt, fmt::Write, str::FromStr};
+use tokio::fs;
+
+/// How the connection to the SMTP server is encrypted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SmtpTls {
+    /// Upgrade a plain connection with `STARTTLS`, which the server must
+    /// offer. Port 587 by default.
+    #[default]
+    StartTls,
+    /// TLS from the first byte. Port 465 by default.
+    Tls,
+    /// No encryption, for a relay on the same host or a test sink. Port 25 by
+    /// default.
+    Plain,
+}
+
+impl SmtpTls {
+    /// All modes.
+    pub const ALL: [Self; 3] = [Self::StartTls, Self::Tls, Self::Plain];
+
+    /// Canonical configuration name, e.g. `starttls`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::StartTls => "starttls",
+            Self::Tls => "tls",
+            Self::Plain => "none",
+        }
+    }
+
+    /// Port used when the server URL does not name one.
+    #[must_use]
+    pub const fn default_port(self) -> u16 {
+        match self {
+            Self::StartTls => 587,
+            Self::Tls => 465,
+            Self::Plain => 25,
+        }
+    }
+}
+
+impl fmt::Display for SmtpTls {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for SmtpTls {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|tls| tls.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|t| t.as_str()).collect();
+                format!(
+                    "Unsupported SMTP TLS mode '{}' (supported: {})",
+                    s.trim(),
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// Where and to whom an email notifier sends.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EmailSpec {
+    /// SMTP server host name.
+    pub host: String,
+    /// SMTP server port.
+    pub port: u16,
+    /// How the connection is encrypted.
+    pub tls: SmtpTls,
+    /// Sender of the messages.
+    pub from: Mailbox,
+    /// Recipients of every message.
+    pub to: Vec<Mailbox>,
+    /// User to authenticate as, with the password in `password_file`.
+    pub username: Option<String>,
+    /// File holding the password of `username`.
+    pub password_file: Option<String>,
+}
+
+impl EmailSpec {
+    /// Settings only email notifiers read, after the `NOTIFIER_{NAME}_` prefix.
+    pub(crate) const SETTINGS: [&str; 5] = ["FROM", "TO", "TLS", "USERNAME", "PASSWORD_FILE"];
+
+    /// Load the settings of email notifier `name` from its `prefix`ed
+    /// settings, sending to the server at `url`.
+    pub(crate) fn load(
+        settings: &Settings,
+        name: &str,
+        prefix: &str,
+        url: Option<&str>,
+        validation: &mut Validation,
+    ) -> Option<Self> {
+        let from_setting = format!("{prefix}FROM");
+        let to_setting = format!("{prefix}TO");
+        let username_setting = format!("{prefix}USERNAME");
+        let password_setting = format!("{prefix}PASSWORD_FILE");
+
+        let tls = validation.parse(settings, &format!("{prefix}TLS"), SmtpTls::StartTls);
+        let server = url.and_then(|url| match parse_server(url, tls) {
+            Ok(server) => Some(server),
+            Err(e) => {
+                validation.invalid(&format!("{prefix}URL"), url, e);
+                None
+            }
+        });
+        let from = validation.parse_with(settings, &from_setting, |value| {
+            value.trim().parse::<Mailbox>()
+        });
+        let to = validation.parse_with(settings, &to_setting, parse_mailboxes);
+        let username = settings.get(&username_setting).map(str::to_owned);
+        let password_file = settings.get(&password_setting).map(str::to_owned);
+
+        for setting in [&from_setting, &to_setting] {
+            if settings.get(setting).is_none() {
+                validation.error(format!("{setting} is required for notifier '{name}'"));
+            }
+        }
+        if username.is_some() != password_file.is_some() {
+            validation.error(format!(
+                "{username_setting} and {password_setting} must be set together for notifier '{name}'"
+            ));
+        }
+
+        let (host, port) = server?;
+        Some(Self {
+            host,
+            port,
+            tls,
+            from: from?,
+            to: to?,
+            username,
+            password_file,
+        })
+    }
+}
+
+/// Sends notifications as email through an SMTP server.
+#[derive(Debug, Clone)]
+pub struct EmailNotifier {
+    name: String,
+    spec: EmailSpec,
+}
+
+impl EmailNotifier {
+    /// Notifier `name` sending as `spec` describes.
+    #[must_use]
+    pub fn new(name: &str, spec: EmailSpec) -> Self {
+        Self {
+            name: name.to_owned(),
+            spec,
+        }
+    }
+
+    /// The message for `notification`.
+    fn message(&self, notification: &Notification) -> Result<Message> {
+        let subject = notification.message.lines().next().unwrap_or_default();
+        let mut builder = Message::builder()
+            .from(self.spec.from.clone())
+            .subject(format!("[{}] {subject}", notification.service));
+        for recipient in &self.spec.to {
+            builder = builder.to(recipient.clone());
+        }
+        builder
+            .multipart(MultiPart::alternative_plain_html(
+                plain_body(notification),
+                html_body(notification),
+            ))
+            .map_err(|e| delivery_failed(&self.name, e))
+    }
+
+    async fn transport(&self) -> Result<AsyncSmtpTransport<Tokio1Executor>> {
+        let spec = &self.spec;
+        let builder = match spec.tls {
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&spec.host),
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&spec.host),
+            SmtpTls::Plain => Ok(AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(
+                &spec.host,
+            )),
+        }
+        .map_err(|e| delivery_failed(&self.name, e))?
+        .port(spec.port);
+
+        let transport = match (&spec.username, &spec.password_file) {
+            (Some(username), Some(password_file)) => {
+                // Read at every send, so a rotated password is picked up
+                let password = fs::read_to_string(password_file).await.map_err(|e| {
+                    delivery_failed(&self.name, format!("reading {password_file}: {e}"))
+                })?;
+                builder.credentials(Credentials::new(
+                    username.clone(),
+                    password.trim().to_owned(),
+                ))
+            }
+            _ => builder,
+        };
+        Ok(transport.build())
+    }
+
+    async fn deliver(&self, notification: &Notification) -> Result<()> {
+        let message = self.message(notification)?;
+        self.transport()
+            .await?
+            .send(message)
+            .await
+            .map_err(|e| delivery_failed(&self.name, e))?;
+        Ok(())
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.deliver(notification))
+    }
+}
+
+/// Labelled facts about `notification`, for the message bodies.
+fn details(notification: &Notification) -> Vec<(&'static str, String)> {
+    let mut details = vec![
+        ("Event", notification.event.to_string()),
+        ("Severity", notification.severity.to_string()),
+    ];
+    if let Some(issuer) = &notification.issuer {
+        details.push(("Issuer", issuer.clone()));
+    }
+    if let Some(previous) = &notification.previous {
+        details.push(("Previous expiry", previous.validity.not_after.to_rfc3339()));
+    }
+    if let Some(info) = &notification.certificate {
+        if let Some(days) = notification.days_remaining() {
+            details.push(("Days remaining", days.to_string()));
+        }
+        details.push(("Expires", info.validity.not_after.to_rfc3339()));
+        details.push(("Serial", info.serial.clone()));
+        details.push(("Fingerprint", info.fingerprint_sha256.clone()));
+    }
+    if let Some(error) = &notification.error {
+        details.push(("Error", error.clone()));
+    }
+    if let Some(next_retry) = notification.next_retry {
+        details.push(("Next retry", next_retry.to_rfc3339()));
+    }
+    details.push(("Time", notification.timestamp.to_rfc3339()));
+    details
+}
+
+fn plain_body(notification: &Notification) -> String {
+    details(notification).into_iter().fold(
+        format!("{}\n\n", notification.message),
+        |mut body, (label, value)| {
+            let _ = writeln!(body, "{label}: {value}");
+            body
+        },
+    )
+}
+
+fn html_body(notification: &Notification) -> String {
+    let rows = details(notification)
+        .into_iter()
+        .fold(String::new(), |mut rows, (label, value)| {
+            let _ = write!(rows, "<tr><th>{label}</th><td>{}</td></tr>", escape(&value));
+            rows
+        });
+    format!(
+        "<p>{}</p>\n<table>{rows}</table>\n",
+        escape(&notification.message).replace('\n', "<br>")
+    )
+}
+
+/// `text` with the characters HTML gives a meaning escaped.
+fn escape(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '&' => "&amp;".to_owned(),
+            '<' => "&lt;".to_owned(),
+            '>' => "&gt;".to_owned(),
+            '"' => "&quot;".to_owned(),
+            c => c.to_string(),
+        })
+        .collect()
+}
+
+/// Parse an `smtp://host[:port]` server URL; the port defaults to the
+/// standard one for `tls`.
+///
+/// # Errors
+///
+/// Returns error if `value` is not an `smtp://` URL with a host, or carries
+/// credentials, which belong in the username and password file settings.
+pub fn parse_server(value: &str, tls: SmtpTls) -> std::result::Result<(String, u16), String> {
+    let url = reqwest::Url::parse(value.trim()).map_err(|e| e.to_string())?;
+    let host = url.host_str().filter(|_| url.scheme() == "smtp");
+    let Some(host) = host.filter(|_| url.path().trim_matches('/').is_empty()) else {
+        return Err("expected smtp://host[:port]".to_owned());
+    };
+    if !url.username().is_empty() || url.password().is_some() {
+        return Err("credentials belong in USERNAME and PASSWORD_FILE".to_owned());
+    }
+    Ok((
+        host.to_owned(),
+        url.port().unwrap_or_else(|| tls.default_port()),
+    ))
+}
+
+/// Parse comma-separated recipients, e.g. `Ops <ops@example.com>, sre@example.com`.
+///
+/// Commas in quoted names, as in `"Ops, Team" <ops@example.com>`, do not
+/// separate recipients.
+///
+/// # Errors
+///
+/// Returns error if a recipient is not an email address or there is none.
+pub fn parse_mailboxes(value: &str) -> std::result::Result<Vec<Mailbox>, String> {
+    let mailboxes = split_unquoted(value)
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            entry
+                .parse::<Mailbox>()
+                .map_err(|e| format!("'{entry}': {e}"))
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    if mailboxes.is_empty() {
+        return Err("expected at least one recipient".to_owned());
+    }
+    Ok(mailboxes)
+}
+
+/// Split `value` at the commas outside double quotes.
+fn split_unquoted(value: &str) -> impl Iterator<Item = &str> {
+    let mut quoted = false;
+    let mut escaped = false;
+    value.split(move |c| {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' => return !quoted,
+            _ => {}
+        }
+        false
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::Event;
+
+    #[test]
+    fn test_parse_server() {
+        assert_eq!(
+            parse_server("smtp://mail.example.com", SmtpTls::StartTls),
+            Ok(("mail.example.com".to_owned(), 587))
+        );
+        assert_eq!(
+            parse_server("smtp://mail.example.com:2525", SmtpTls::Tls),
+            Ok(("mail.example.com".to_owned(), 2525))
+        );
+        assert_eq!(
+            parse_server("smtp://localhost", SmtpTls::Plain),
+            Ok(("localhost".to_owned(), 25))
+        );
+        assert!(parse_server("https://mail.example.com", SmtpTls::Tls).is_err());
+        assert!(parse_server("smtp://user:pw@mail.example.com", SmtpTls::Tls).is_err());
+
+        assert_eq!(
+            parse_mailboxes("Ops <ops@example.com>, sre@example.com")
+                .unwrap()
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            ["Ops <ops@example.com>", "sre@example.com"]
+        );
+        assert_eq!(
+            parse_mailboxes(r#""Ops, Team" <ops@example.com>,"On \"call\", EU" <eu@example.com>"#)
+                .unwrap()
+                .iter()
+                .map(|mailbox| mailbox.name.clone())
+                .collect::<Vec<_>>(),
+            [
+                Some("Ops, Team".to_owned()),
+                Some(r#"On "call", EU"#.to_owned())
+            ]
+        );
+        assert!(parse_mailboxes("ops").is_err());
+        assert!(parse_mailboxes(" , ").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_unreadable_password_file() {
+        let notifier = EmailNotifier::new(
+            "mail",
+            EmailSpec {
+                host: "localhost".to_owned(),
+                port: 25,
+                tls: SmtpTls::Plain,
+                from: "bridge@example.com".parse().unwrap(),
+                to: parse_mailboxes("ops@example.com").unwrap(),
+                username: Some("bridge".to_owned()),
+                password_file: Some("/nonexistent/smtp-password".to_owned()),
+            },
+        );
+        let error = notifier.transport().await.unwrap_err();
+        assert!(matches!(error, crate::Error::Notification { .. }));
+        assert!(error.to_string().contains("/nonexistent/smtp-password"));
+    }
+
+    #[test]
+    fn test_bodies() {
+        let mut notification = Notification::new("api", Event::Failed, "❌ <reload> failed");
+        notification.error = Some("exit status 1".to_owned());
+
+        let plain = plain_body(&notification);
+        assert!(plain.starts_with("❌ <reload> failed\n\nEvent: failed\nSeverity: error\n"));
+        assert!(plain.contains("Error: exit status 1\n"));
+
+        let html = html_body(&notification);
+        assert!(html.starts_with("<p>❌ &lt;reload&gt; failed</p>"));
+        assert!(html.contains("<tr><th>Error</th><td>exit status 1</td></tr>"));
+    }
+}
@@ -0,0 +1,225 @@
+//! Slack incoming webhooks.
+
+use super::{Notification, Notifier, Severity, send_request};
+use crate::Result;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+
+/// Posts notifications to a Slack incoming webhook.
+///
+/// The message is a Block Kit attachment colored by severity, listing the
+/// certificate details; the plain `text` is what Slack shows in alerts.
+#[derive(Debug, Clone)]
+pub struct SlackNotifier {
+    name: String,
+    webhook_url: String,
+    client: reqwest::Client,
+}
+
+impl SlackNotifier {
+    /// Notifier `name` posting to `webhook_url` with `client`.
+    #[must_use]
+    pub fn new(name: &str, webhook_url: &str, client: reqwest::Client) -> Self {
+        Self {
+            name: name.to_owned(),
+            webhook_url: webhook_url.to_owned(),
+            client,
+        }
+    }
+}
+
+impl Notifier for SlackNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        let request = self
+            .client
+            .post(&self.webhook_url)
+            .json(&payload(notification));
+        Box::pin(send_request(&self.name, request))
+    }
+}
+
+/// Attachment color of `severity`.
+const fn color(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "#2eb886",
+        Severity::Warning => "#daa038",
+        Severity::Error => "#a30200",
+    }
+}
+
+/// The webhook payload for `notification`.
+fn payload(notification: &Notification) -> Value {
+    let mut fields = vec![
+        field("Service", &escape(&notification.service)),
+        field("Event", notification.event.as_str()),
+    ];
+    if let Some(issuer) = &notification.issuer {
+        fields.push(field("Issuer", &escape(issuer)));
+    }
+    if let Some(previous) = &notification.previous {
+        fields.push(field(
+            "Previous expiry",
+            &format_time(previous.validity.not_after),
+        ));
+    }
+    let certificate = notification.certificate.as_ref();
+    if let (Some(info), Some(days)) = (certificate, notification.days_remaining()) {
+        let expiry = format!("{} ({days} days)", format_time(info.validity.not_after));
+        fields.push(field("Expires", &expiry));
+        fields.push(field("Serial", &format!("`{}`", info.serial)));
+        fields.push(field(
+            "Fingerprint (SHA-256)",
+            &format!("`{}`", info.fingerprint_sha256),
+        ));
+    }
+    if let Some(next_retry) = notification.next_retry {
+        fields.push(field("Next retry", &format_time(next_retry)));
+    }
+
+    let mut blocks = vec![
+        section(&format!(
+            "*[{}]* {}",
+            escape(&notification.service),
+            escape(&notification.message)
+        )),
+        json!({ "type": "section", "fields": fields }),
+    ];
+    if let Some(info) = certificate.filter(|info| !info.subject_alt_names.is_empty()) {
+        let sans: Vec<_> = info
+            .subject_alt_names
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        blocks.push(section(&format!(
+            "*Subject alternative names*\n{}",
+            escape(&sans.join(", "))
+        )));
+    }
+    if let Some(error) = &notification.error {
+        blocks.push(section(&format!("*Error*\n```{}```", escape(error))));
+    }
+    blocks.push(json!({
+        "type": "context",
+        "elements": [{
+            "type": "mrkdwn",
+            "text": format!(
+                "{} · {}",
+                notification.severity,
+                format_time(notification.timestamp)
+            ),
+        }],
+    }));
+
+    json!({
+        "text": format!(
+            "[{}] {}",
+            escape(&notification.service),
+            escape(&notification.message)
+        ),
+        "username": "cert-manager",
+        "icon_emoji": ":lock:",
+        "attachments": [{
+            "color": color(notification.severity),
+            "blocks": blocks,
+        }],
+    })
+}
+
+fn section(text: &str) -> Value {
+    json!({ "type": "section", "text": { "type": "mrkdwn", "text": text } })
+}
+
+/// A `*label*` over `value`, which is Slack markup.
+fn field(label: &str, value: &str) -> Value {
+    json!({ "type": "mrkdwn", "text": format!("*{label}*\n{value}") })
+}
+
+fn format_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M UTC").to_string()
+}
+
+/// `text` with the characters Slack uses for links and mentions escaped.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CertificateInfo, certificate, notifier::Event};
+
+    fn certificate_info() -> CertificateInfo {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let params = rcgen::CertificateParams::new(vec!["api.example.com".to_owned()]).unwrap();
+        let cert = params.self_signed(&key).unwrap();
+        certificate::parse_certificate_info(cert.pem().as_bytes()).unwrap()
+    }
+
+    fn texts(blocks: &Value) -> Vec<String> {
+        let mut texts = Vec::new();
+        for block in blocks.as_array().unwrap() {
+            let items = block["fields"]
+                .as_array()
+                .or_else(|| block["elements"].as_array());
+            for item in items.into_iter().flatten().chain([&block["text"]]) {
+                if let Some(text) = item["text"].as_str() {
+                    texts.push(text.to_owned());
+                }
+            }
+        }
+        texts
+    }
+
+    #[test]
+    fn test_renewal_payload() {
+        let mut notification = Notification::new("api", Event::Renewed, "✅ Renewed");
+        let info = certificate_info();
+        notification.previous = Some(info.clone());
+        notification.certificate = Some(info.clone());
+        notification.issuer = Some("step-ca".to_owned());
+
+        let payload = payload(&notification);
+        assert_eq!(payload["text"], "[api] ✅ Renewed");
+        assert_eq!(payload["attachments"][0]["color"], "#2eb886");
+        let texts = texts(&payload["attachments"][0]["blocks"]);
+        for expected in [
+            "*[api]* ✅ Renewed".to_owned(),
+            "*Issuer*\nstep-ca".to_owned(),
+            format!(
+                "*Previous expiry*\n{}",
+                format_time(info.validity.not_after)
+            ),
+            format!("*Serial*\n`{}`", info.serial),
+            format!("*Fingerprint (SHA-256)*\n`{}`", info.fingerprint_sha256),
+            "*Subject alternative names*\nDNS:api.example.com".to_owned(),
+        ] {
+            assert!(texts.contains(&expected), "{expected} not in {texts:#?}");
+        }
+    }
+
+    #[test]
+    fn test_failure_payload() {
+        let mut notification = Notification::new("api", Event::Failed, "❌ <reload> failed");
+        notification.error = Some("exit status 1 & <stderr>".to_owned());
+        notification.next_retry = Some(notification.timestamp);
+
+        let payload = payload(&notification);
+        assert_eq!(payload["attachments"][0]["color"], "#a30200");
+        assert_eq!(payload["text"], "[api] ❌ &lt;reload&gt; failed");
+        let texts = texts(&payload["attachments"][0]["blocks"]);
+        assert!(texts.contains(&"*[api]* ❌ &lt;reload&gt; failed".to_owned()));
+        assert!(texts.contains(&"*Error*\n```exit status 1 &amp; &lt;stderr&gt;```".to_owned()));
+        assert!(texts.contains(&format!(
+            "*Next retry*\n{}",
+            format_time(notification.timestamp)
+        )));
+        assert!(!texts.iter().any(|text| text.starts_with("*Serial*")));
+    }
+}
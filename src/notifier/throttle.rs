@@ -0,0 +1,309 @@
+//! Holding back repeated notifications.
+//!
+//! The daemon checks every certificate again and again, so a lasting
+//! problem would be announced at every check. What it sends is held back
+//! while the same notification went out within the dedup window of its
+//! event, and everything shares an optional rate limit. Failed checks in a
+//! row are summarized instead of repeated, a passing outage is only logged,
+//! and they are escalated after a number of attempts and closed by a single
+//! recovery notice.
+
+use super::Event;
+use crate::duration;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    str::FromStr,
+};
+
+/// Dedup window of the events that repeat at every check while their cause
+/// lasts, unless configured otherwise.
+pub const DEFAULT_DEDUP_WINDOW: chrono::Duration = chrono::Duration::hours(6);
+
+/// Dedup windows used unless configured otherwise: [`DEFAULT_DEDUP_WINDOW`]
+/// for clock skew, backup failures and failed checks, none for the rest.
+#[must_use]
+pub fn default_dedup_windows() -> Vec<(Event, chrono::Duration)> {
+    [Event::ClockSkew, Event::BackupFailed, Event::Failed]
+        .map(|event| (event, DEFAULT_DEDUP_WINDOW))
+        .to_vec()
+}
+
+/// Parse comma-separated `event=duration` dedup windows, e.g.
+/// `failed=1h,clock-skew=1d`, on top of [`default_dedup_windows`]. A bare
+/// number counts in hours and `0` turns deduplication off for the event.
+///
+/// # Errors
+///
+/// Returns error if an entry has no `=`, names an unknown event or has an
+/// invalid duration.
+pub fn parse_dedup_windows(value: &str) -> Result<Vec<(Event, chrono::Duration)>, String> {
+    let mut windows = default_dedup_windows();
+    for entry in value.split(',').filter(|entry| !entry.trim().is_empty()) {
+        let (event, window) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("expected 'event=duration', got '{}'", entry.trim()))?;
+        let event: Event = event.parse()?;
+        let window = duration::parse(window, chrono::Duration::hours(1))?;
+        match windows.iter_mut().find(|(other, _)| *other == event) {
+            Some(existing) => existing.1 = window,
+            None => windows.push((event, window)),
+        }
+    }
+    Ok(windows)
+}
+
+/// At most `count` notifications every `period`, e.g. `20/1h`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    /// Notifications sent within `period` before the rest are dropped.
+    pub count: usize,
+    /// Length of the sliding window.
+    pub period: chrono::Duration,
+}
+
+impl fmt::Display for RateLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.count, duration::format(self.period))
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (count, period) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected 'count/period' such as 20/1h, got '{}'", s.trim()))?;
+        let count = count
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|count| *count > 0)
+            .ok_or_else(|| "expected a count greater than zero".to_owned())?;
+        let period = duration::parse(period, chrono::Duration::hours(1))?;
+        if period <= chrono::Duration::zero() {
+            return Err("the period must be greater than zero".to_owned());
+        }
+        Ok(Self { count, period })
+    }
+}
+
+/// When recent notifications were sent, to enforce a [`RateLimit`].
+#[derive(Debug, Default)]
+pub(crate) struct SentLog {
+    times: VecDeque<DateTime<Utc>>,
+}
+
+impl SentLog {
+    /// Whether a notification may be sent at `now` under `limit`, counting
+    /// it when it may.
+    pub(crate) fn allow(&mut self, limit: RateLimit, now: DateTime<Utc>) -> bool {
+        while self
+            .times
+            .front()
+            .is_some_and(|sent| now - *sent >= limit.period)
+        {
+            self.times.pop_front();
+        }
+        if self.times.len() >= limit.count {
+            return false;
+        }
+        self.times.push_back(now);
+        true
+    }
+}
+
+/// Checks of a certificate that failed in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Failures {
+    /// Failed checks so far.
+    pub attempts: u32,
+    /// When the first of them failed.
+    pub since: DateTime<Utc>,
+    /// When the last alert about them was sent, if any.
+    pub alerted: Option<DateTime<Utc>>,
+    /// Whether an alert went to the escalation sinks.
+    pub escalated: bool,
+}
+
+/// What the daemon recently told about one certificate.
+#[derive(Debug, Default)]
+pub(crate) struct History {
+    /// Until when each event and message is held back.
+    held: HashMap<(Event, String), DateTime<Utc>>,
+    failures: Option<Failures>,
+}
+
+impl History {
+    /// Whether `event` with `message` is still held back at `now`.
+    pub(crate) fn is_held(&self, event: Event, message: &str, now: DateTime<Utc>) -> bool {
+        self.held
+            .get(&(event, message.to_owned()))
+            .is_some_and(|until| now < *until)
+    }
+
+    /// Hold back `event` with `message`, sent at `now`, for `window`.
+    pub(crate) fn hold(
+        &mut self,
+        event: Event,
+        message: &str,
+        now: DateTime<Utc>,
+        window: chrono::Duration,
+    ) {
+        self.held.retain(|_, until| now < *until);
+        if window > chrono::Duration::zero() {
+            self.held.insert((event, message.to_owned()), now + window);
+        }
+    }
+
+    /// Count a check that failed at `now`, returning the failures in a row
+    /// when they are to be alerted on, as they are once [`Self::alerted`].
+    ///
+    /// They are at most once every `window`, and a `transient` failure, which
+    /// may clear up by itself before it matters, only once it has lasted that
+    /// long. After `escalate_after` of them they are escalated, and the first
+    /// escalation is alerted on right away.
+    pub(crate) fn fail(
+        &mut self,
+        now: DateTime<Utc>,
+        transient: bool,
+        window: chrono::Duration,
+        escalate_after: Option<u32>,
+    ) -> Option<Failures> {
+        let failures = self.failures.get_or_insert(Failures {
+            attempts: 0,
+            since: now,
+            alerted: None,
+            escalated: false,
+        });
+        failures.attempts = failures.attempts.saturating_add(1);
+
+        let escalate = escalate_after.is_some_and(|attempts| failures.attempts >= attempts);
+        let due = failures
+            .alerted
+            .is_none_or(|alerted| now - alerted >= window)
+            || (escalate && !failures.escalated);
+        let passing = transient && now - failures.since < window;
+        if !due || (passing && !escalate) {
+            return None;
+        }
+        Some(Failures {
+            alerted: Some(now),
+            escalated: escalate,
+            ..*failures
+        })
+    }
+
+    /// Record that the alert about `failures`, as returned by
+    /// [`Self::fail`], was sent.
+    pub(crate) const fn alerted(&mut self, failures: Failures) {
+        if let Some(current) = &mut self.failures {
+            current.alerted = failures.alerted;
+            current.escalated = failures.escalated;
+        }
+    }
+
+    /// End the failures in a row after a successful check, returning them.
+    pub(crate) const fn recover(&mut self) -> Option<Failures> {
+        self.failures.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dedup_windows() {
+        let windows = parse_dedup_windows("failed=1h, renewed=30m,clock-skew=0").unwrap();
+        assert_eq!(
+            windows,
+            [
+                (Event::ClockSkew, chrono::Duration::zero()),
+                (Event::BackupFailed, DEFAULT_DEDUP_WINDOW),
+                (Event::Failed, chrono::Duration::hours(1)),
+                (Event::Renewed, chrono::Duration::minutes(30)),
+            ]
+        );
+        assert_eq!(parse_dedup_windows(""), Ok(default_dedup_windows()));
+        assert!(parse_dedup_windows("failed").is_err());
+        assert!(parse_dedup_windows("crashed=1h").is_err());
+        assert!(parse_dedup_windows("failed=soon").is_err());
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let limit: RateLimit = "2/1h".parse().unwrap();
+        assert_eq!(limit.to_string(), "2/1h");
+        assert!("0/1h".parse::<RateLimit>().is_err());
+        assert!("5/0s".parse::<RateLimit>().is_err());
+        assert!("5".parse::<RateLimit>().is_err());
+
+        let start = Utc::now();
+        let mut log = SentLog::default();
+        assert!(log.allow(limit, start));
+        assert!(log.allow(limit, start + chrono::Duration::minutes(10)));
+        assert!(!log.allow(limit, start + chrono::Duration::minutes(20)));
+        assert!(log.allow(limit, start + chrono::Duration::hours(1)));
+        assert!(!log.allow(limit, start + chrono::Duration::minutes(65)));
+    }
+
+    #[test]
+    fn test_history() {
+        let now = Utc::now();
+        let mut history = History::default();
+        history.hold(Event::ClockSkew, "skewed", now, chrono::Duration::hours(1));
+        history.hold(Event::Renewed, "renewed", now, chrono::Duration::zero());
+        assert!(history.is_held(Event::ClockSkew, "skewed", now));
+        assert!(!history.is_held(Event::ClockSkew, "skewed again", now));
+        assert!(!history.is_held(Event::Renewed, "renewed", now));
+        assert!(!history.is_held(Event::ClockSkew, "skewed", now + chrono::Duration::hours(1)));
+
+        let window = chrono::Duration::hours(1);
+        let minutes = |count| now + chrono::Duration::minutes(count);
+        // Every alert the tests ask for is sent
+        let fail = |history: &mut History, at, transient, escalate_after| {
+            let failures = history.fail(at, transient, window, escalate_after);
+            if let Some(failures) = failures {
+                history.alerted(failures);
+            }
+            failures
+        };
+        let alerted = fail(&mut history, now, false, Some(3)).unwrap();
+        assert_eq!((alerted.attempts, alerted.escalated), (1, false));
+        assert_eq!(fail(&mut history, minutes(10), false, Some(3)), None);
+        // The first escalation is not held back
+        let escalated = fail(&mut history, minutes(20), true, Some(3)).unwrap();
+        assert_eq!(
+            (escalated.attempts, escalated.since, escalated.escalated),
+            (3, now, true)
+        );
+        assert_eq!(fail(&mut history, minutes(30), true, Some(3)), None);
+        let summary = fail(&mut history, minutes(80), true, Some(3)).unwrap();
+        assert_eq!(summary.attempts, 5);
+        assert_eq!(history.recover(), Some(summary));
+        assert_eq!(history.recover(), None);
+
+        // Transient failures are only logged while they may pass
+        assert_eq!(fail(&mut history, now, true, None), None);
+        assert_eq!(fail(&mut history, minutes(50), true, None), None);
+        assert_eq!(
+            history.recover().map(|failures| failures.alerted),
+            Some(None)
+        );
+        assert_eq!(fail(&mut history, now, true, None), None);
+        let lasting = fail(&mut history, minutes(60), true, None).unwrap();
+        assert_eq!(lasting.attempts, 2);
+        assert_eq!(fail(&mut history, minutes(90), true, None), None);
+        assert!(fail(&mut history, minutes(120), true, None).is_some());
+        history.recover();
+
+        // An alert that was not sent is due again at the next failure
+        assert!(history.fail(now, false, window, None).is_some());
+        let retried = fail(&mut history, minutes(1), false, None).unwrap();
+        assert_eq!(retried.attempts, 2);
+        assert_eq!(fail(&mut history, minutes(2), false, None), None);
+    }
+}
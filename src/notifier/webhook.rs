@@ -0,0 +1,438 @@
+//! Generic JSON webhooks.
+//!
+//! The body is a JSON template whose strings may hold `{{placeholder}}`s,
+//! so incident tools, Mattermost, Teams or Discord can be posted to without
+//! code changes. A string that is a single placeholder takes the value's JSON
+//! type, e.g. `"{{days_remaining}}"` becomes a number, or `null` when there is
+//! no certificate.
+
+use super::{Notification, Notifier, delivery_failed, send_request};
+use crate::{
+    Result,
+    certificate::to_hex,
+    settings::{Settings, Validation},
+};
+use aws_lc_rs::hmac;
+use futures::future::BoxFuture;
+use reqwest::header::{CONTENT_TYPE, HeaderName, HeaderValue};
+use serde_json::{Map, Value, json};
+use tokio::fs;
+
+/// Header carrying the body signature unless configured otherwise.
+pub const SIGNATURE_HEADER: &str = "X-Signature-256";
+
+/// Placeholders a template may use.
+pub const PLACEHOLDERS: [&str; 14] = [
+    "service",
+    "event",
+    "severity",
+    "message",
+    "days_remaining",
+    "not_after",
+    "previous_not_after",
+    "serial",
+    "fingerprint",
+    "issuer",
+    "error",
+    "next_retry",
+    "escalated",
+    "timestamp",
+];
+
+/// Body template, headers and signing of a webhook notifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebhookSpec {
+    /// JSON body with `{{placeholder}}`s; `None` posts every field.
+    pub template: Option<Value>,
+    /// Extra request headers.
+    pub headers: Vec<(String, String)>,
+    /// File holding the key the body is signed with using HMAC-SHA256.
+    pub secret_file: Option<String>,
+    /// Header carrying the `sha256=` signature.
+    pub signature_header: String,
+}
+
+impl Default for WebhookSpec {
+    fn default() -> Self {
+        Self {
+            template: None,
+            headers: Vec::new(),
+            secret_file: None,
+            signature_header: SIGNATURE_HEADER.to_owned(),
+        }
+    }
+}
+
+impl WebhookSpec {
+    /// Settings only webhook notifiers read, after the `NOTIFIER_{NAME}_`
+    /// prefix.
+    pub(crate) const SETTINGS: [&str; 4] =
+        ["TEMPLATE", "HEADERS", "SECRET_FILE", "SIGNATURE_HEADER"];
+
+    /// Load the settings of a webhook notifier from its `prefix`ed settings.
+    pub(crate) fn load(settings: &Settings, prefix: &str, validation: &mut Validation) -> Self {
+        let template =
+            validation.parse_with(settings, &format!("{prefix}TEMPLATE"), parse_template);
+        let headers = validation
+            .parse_with(settings, &format!("{prefix}HEADERS"), parse_headers)
+            .unwrap_or_default();
+        let secret_file = settings
+            .get(&format!("{prefix}SECRET_FILE"))
+            .map(str::to_owned);
+        let signature_header = validation
+            .parse_with(
+                settings,
+                &format!("{prefix}SIGNATURE_HEADER"),
+                parse_header_name,
+            )
+            .unwrap_or_else(|| SIGNATURE_HEADER.to_owned());
+        Self {
+            template,
+            headers,
+            secret_file,
+            signature_header,
+        }
+    }
+}
+
+/// Posts notifications as JSON documents, for on-call and chat tools that
+/// accept arbitrary webhooks.
+///
+/// Without a template the document carries every placeholder as a field.
+#[derive(Debug, Clone)]
+pub struct WebhookNotifier {
+    name: String,
+    url: String,
+    client: reqwest::Client,
+    template: Option<Value>,
+    headers: Vec<(String, String)>,
+    signing: Option<Signing>,
+}
+
+/// Where the signing key is and which header carries the signature.
+#[derive(Debug, Clone)]
+struct Signing {
+    secret_file: String,
+    header: String,
+}
+
+impl WebhookNotifier {
+    /// Notifier `name` posting to `url` with `client`.
+    #[must_use]
+    pub fn new(name: &str, url: &str, client: reqwest::Client) -> Self {
+        Self {
+            name: name.to_owned(),
+            url: url.to_owned(),
+            client,
+            template: None,
+            headers: Vec::new(),
+            signing: None,
+        }
+    }
+
+    /// Post `template` with its placeholders filled in instead of every field.
+    #[must_use]
+    pub fn with_template(mut self, template: Value) -> Self {
+        self.template = Some(template);
+        self
+    }
+
+    /// Send `headers` along with every request.
+    #[must_use]
+    pub fn with_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Sign the body with HMAC-SHA256 using the key in `secret_file`, and
+    /// send the signature as `sha256=<hex>` in `header`.
+    #[must_use]
+    pub fn signed(mut self, secret_file: &str, header: &str) -> Self {
+        self.signing = Some(Signing {
+            secret_file: secret_file.to_owned(),
+            header: header.to_owned(),
+        });
+        self
+    }
+
+    /// The body for `notification`.
+    fn body(&self, notification: &Notification) -> Value {
+        let fields = placeholders(notification);
+        self.template.as_ref().map_or_else(
+            || Value::Object(fields.clone()),
+            |template| render(template, &fields),
+        )
+    }
+
+    async fn post(&self, notification: &Notification) -> Result<()> {
+        let body = self.body(notification).to_string();
+
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(CONTENT_TYPE, "application/json");
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        if let Some(signing) = &self.signing {
+            // Read at every send, so a rotated key is picked up
+            let secret = fs::read_to_string(&signing.secret_file)
+                .await
+                .map_err(|e| {
+                    delivery_failed(
+                        &self.name,
+                        format_args!("signing key {}: {e}", signing.secret_file),
+                    )
+                })?;
+            request = request.header(&signing.header, signature(secret.trim(), &body));
+        }
+
+        send_request(&self.name, request.body(body)).await
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.post(notification))
+    }
+}
+
+/// The `sha256=<hex>` HMAC-SHA256 signature of `body` with `secret`.
+#[must_use]
+pub fn signature(secret: &str, body: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    format!(
+        "sha256={}",
+        to_hex(hmac::sign(&key, body.as_bytes()).as_ref())
+    )
+}
+
+/// The value of each placeholder for `notification`.
+fn placeholders(notification: &Notification) -> Map<String, Value> {
+    let certificate = notification.certificate.as_ref();
+    let fields = json!({
+        "service": notification.service,
+        "event": notification.event.as_str(),
+        "severity": notification.severity.as_str(),
+        "message": notification.message,
+        "days_remaining": notification.days_remaining(),
+        "not_after": certificate.map(|info| info.validity.not_after.to_rfc3339()),
+        "previous_not_after": notification
+            .previous
+            .as_ref()
+            .map(|info| info.validity.not_after.to_rfc3339()),
+        "serial": certificate.map(|info| &info.serial),
+        "fingerprint": certificate.map(|info| &info.fingerprint_sha256),
+        "issuer": notification.issuer,
+        "error": notification.error,
+        "next_retry": notification.next_retry.map(|time| time.to_rfc3339()),
+        "escalated": notification.escalated,
+        "timestamp": notification.timestamp.to_rfc3339(),
+    });
+    match fields {
+        Value::Object(fields) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// `template` with its placeholders replaced by `fields`.
+fn render(template: &Value, fields: &Map<String, Value>) -> Value {
+    match template {
+        Value::String(text) => render_text(text, fields),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| render(item, fields)).collect())
+        }
+        Value::Object(entries) => Value::Object(
+            entries
+                .iter()
+                .map(|(key, value)| (key.clone(), render(value, fields)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+fn render_text(text: &str, fields: &Map<String, Value>) -> Value {
+    // A lone placeholder keeps the type of its value
+    if let [Segment::Placeholder(name)] = segments(text).as_slice()
+        && let Some(value) = fields.get(*name)
+    {
+        return value.clone();
+    }
+
+    let rendered = segments(text)
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Text(text) => text.to_owned(),
+            Segment::Placeholder(name) => match fields.get(name) {
+                Some(Value::String(value)) => value.clone(),
+                Some(Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            },
+        })
+        .collect();
+    Value::String(rendered)
+}
+
+/// A piece of a template string.
+#[derive(Debug, PartialEq, Eq)]
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+/// Split `text` into literal text and `{{placeholder}}` names.
+fn segments(text: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    while let Some((before, after)) = rest.split_once("{{") {
+        let Some((name, after)) = after.split_once("}}") else {
+            break;
+        };
+        if !before.is_empty() {
+            segments.push(Segment::Text(before));
+        }
+        segments.push(Segment::Placeholder(name.trim()));
+        rest = after;
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+/// Names of the `{{placeholder}}`s in the strings of `template`.
+fn template_placeholders<'a>(template: &'a Value, names: &mut Vec<&'a str>) {
+    match template {
+        Value::String(text) => {
+            for segment in segments(text) {
+                if let Segment::Placeholder(name) = segment {
+                    names.push(name);
+                }
+            }
+        }
+        Value::Array(items) => {
+            for item in items {
+                template_placeholders(item, names);
+            }
+        }
+        Value::Object(entries) => {
+            for value in entries.values() {
+                template_placeholders(value, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Parse a body template.
+///
+/// # Errors
+///
+/// Returns error if the template is not JSON or uses an unknown placeholder.
+pub fn parse_template(value: &str) -> std::result::Result<Value, String> {
+    let template: Value =
+        serde_json::from_str(value).map_err(|e| format!("expected a JSON template: {e}"))?;
+    let mut names = Vec::new();
+    template_placeholders(&template, &mut names);
+    if let Some(unknown) = names.iter().find(|name| !PLACEHOLDERS.contains(name)) {
+        return Err(format!(
+            "unknown placeholder {{{{{unknown}}}}} (supported: {})",
+            PLACEHOLDERS.join(", ")
+        ));
+    }
+    Ok(template)
+}
+
+/// Parse `Name: value` headers, one per line since values may hold commas
+/// and semicolons.
+///
+/// # Errors
+///
+/// Returns error if an entry has no `:` or is not a valid header.
+pub fn parse_headers(value: &str) -> std::result::Result<Vec<(String, String)>, String> {
+    value
+        .lines()
+        .filter(|entry| !entry.trim().is_empty())
+        .map(|entry| {
+            let (name, value) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("expected 'Name: value', got '{}'", entry.trim()))?;
+            let name = parse_header_name(name)?;
+            HeaderValue::from_str(value.trim())
+                .map_err(|_| format!("invalid value for header {name}"))?;
+            Ok((name, value.trim().to_owned()))
+        })
+        .collect()
+}
+
+/// Parse a header name.
+///
+/// # Errors
+///
+/// Returns error if `value` is not a valid header name.
+pub fn parse_header_name(value: &str) -> std::result::Result<String, String> {
+    let name = value.trim();
+    HeaderName::from_bytes(name.as_bytes())
+        .map(|_| name.to_owned())
+        .map_err(|_| format!("invalid header name '{name}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifier::Event;
+
+    #[test]
+    fn test_template_rendering() {
+        let template = parse_template(
+            r#"{"text": "[{{service}}] {{message}} ({{days_remaining}}d left)",
+                "days": "{{days_remaining}}", "serial": "{{ serial }}", "tags": ["{{event}}"]}"#,
+        )
+        .unwrap();
+        let notification = Notification::new("api", Event::Failed, "❌ \"quoted\"");
+
+        let body = render(&template, &placeholders(&notification));
+        assert_eq!(
+            body,
+            json!({
+                "text": "[api] ❌ \"quoted\" (d left)",
+                "days": null,
+                "serial": null,
+                "tags": ["failed"],
+            })
+        );
+
+        assert!(parse_template("{\"text\": \"{{expiry}}\"}").is_err());
+        assert!(parse_template("text={{message}}").is_err());
+    }
+
+    #[test]
+    fn test_signature() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            signature("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        assert_eq!(
+            parse_headers(
+                "Authorization: Bearer abc\nAccept: application/json, */*\n\nX-Team:platform"
+            ),
+            Ok(vec![
+                ("Authorization".to_owned(), "Bearer abc".to_owned()),
+                ("Accept".to_owned(), "application/json, */*".to_owned()),
+                ("X-Team".to_owned(), "platform".to_owned()),
+            ])
+        );
+        assert!(parse_headers("Authorization Bearer abc").is_err());
+        assert!(parse_headers("Bad Name: x").is_err());
+    }
+}
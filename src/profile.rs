@@ -0,0 +1,533 @@
+//! Certificate profiles: subject fields, key usage and policies.
+//!
+//! A profile describes everything in a certificate besides its key, names and
+//! validity. Every issuance backend applies the same profile: self-signed
+//! certificates and CSRs get it directly, the `step` CLI gets it as a
+//! template, and step-ca receives it as template data.
+
+use crate::san::SubjectAltName;
+use rcgen::{
+    CertificateParams, CustomExtension, DistinguishedName, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyUsagePurpose,
+};
+use serde_json::{Map, Value, json};
+use std::{fmt, str::FromStr};
+
+/// OID of the certificate policies extension.
+const CERTIFICATE_POLICIES: [u64; 4] = [2, 5, 29, 32];
+
+/// What a certificate may be used for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ProfileKind {
+    /// TLS server authentication.
+    #[default]
+    Server,
+    /// TLS client authentication.
+    Client,
+    /// Both TLS server and client authentication.
+    ServerClient,
+}
+
+impl ProfileKind {
+    /// All supported profiles.
+    pub const ALL: [Self; 3] = [Self::Server, Self::Client, Self::ServerClient];
+
+    /// Canonical configuration name, e.g. `server-client`.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Server => "server",
+            Self::Client => "client",
+            Self::ServerClient => "server-client",
+        }
+    }
+
+    /// Extended key usages granted by this profile.
+    #[must_use]
+    pub fn extended_key_usages(self) -> Vec<ExtendedKeyUsagePurpose> {
+        match self {
+            Self::Server => vec![ExtendedKeyUsagePurpose::ServerAuth],
+            Self::Client => vec![ExtendedKeyUsagePurpose::ClientAuth],
+            Self::ServerClient => vec![
+                ExtendedKeyUsagePurpose::ServerAuth,
+                ExtendedKeyUsagePurpose::ClientAuth,
+            ],
+        }
+    }
+
+    /// Key usages granted by this profile. Servers keep key encipherment for
+    /// RSA key transport; clients only ever sign.
+    #[must_use]
+    pub fn key_usages(self) -> Vec<KeyUsagePurpose> {
+        match self {
+            Self::Client => vec![KeyUsagePurpose::DigitalSignature],
+            Self::Server | Self::ServerClient => vec![
+                KeyUsagePurpose::DigitalSignature,
+                KeyUsagePurpose::KeyEncipherment,
+            ],
+        }
+    }
+
+    /// Names of the key usages in step template syntax.
+    fn step_key_usages(self) -> Vec<&'static str> {
+        match self {
+            Self::Client => vec!["digitalSignature"],
+            Self::Server | Self::ServerClient => vec!["digitalSignature", "keyEncipherment"],
+        }
+    }
+
+    /// Names of the extended key usages in step template syntax.
+    fn step_extended_key_usages(self) -> Vec<&'static str> {
+        match self {
+            Self::Server => vec!["serverAuth"],
+            Self::Client => vec!["clientAuth"],
+            Self::ServerClient => vec!["serverAuth", "clientAuth"],
+        }
+    }
+}
+
+impl fmt::Display for ProfileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ProfileKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized = s.trim().to_ascii_lowercase().replace('+', "-");
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == normalized)
+            .ok_or_else(|| {
+                let supported: Vec<_> = Self::ALL.iter().map(|k| k.as_str()).collect();
+                format!(
+                    "Unsupported certificate profile '{s}' (supported: {})",
+                    supported.join(", ")
+                )
+            })
+    }
+}
+
+/// Subject fields besides the common name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Subject {
+    /// Two-letter country code (`C`).
+    pub country: Option<String>,
+    /// State or province (`ST`).
+    pub state: Option<String>,
+    /// Locality (`L`).
+    pub locality: Option<String>,
+    /// Organization (`O`).
+    pub organization: Option<String>,
+    /// Organizational unit (`OU`).
+    pub organizational_unit: Option<String>,
+}
+
+impl Subject {
+    /// The historical default subject: `C=KR, O={service_name} Service`.
+    #[must_use]
+    pub fn for_service(service_name: &str) -> Self {
+        Self {
+            country: Some("KR".to_owned()),
+            organization: Some(format!("{service_name} Service")),
+            ..Self::default()
+        }
+    }
+
+    /// Fields in DN order, with their attribute type and short name.
+    const fn fields(&self) -> [(DnType, &'static str, Option<&String>); 5] {
+        [
+            (DnType::CountryName, "C", self.country.as_ref()),
+            (DnType::StateOrProvinceName, "ST", self.state.as_ref()),
+            (DnType::LocalityName, "L", self.locality.as_ref()),
+            (DnType::OrganizationName, "O", self.organization.as_ref()),
+            (
+                DnType::OrganizationalUnitName,
+                "OU",
+                self.organizational_unit.as_ref(),
+            ),
+        ]
+    }
+
+    /// Distinguished name with these fields followed by `common_name`.
+    #[must_use]
+    pub fn distinguished_name(&self, common_name: &str) -> DistinguishedName {
+        let mut name = DistinguishedName::new();
+        for (dn_type, _, value) in self.fields() {
+            if let Some(value) = value {
+                name.push(dn_type, value.as_str());
+            }
+        }
+        name.push(DnType::CommonName, common_name);
+        name
+    }
+
+    /// Subject in step template syntax, e.g. `{"country": "KR", ...}`.
+    fn step_template(&self, common_name: &str) -> Value {
+        let mut subject = Map::new();
+        for (key, value) in [
+            ("country", &self.country),
+            ("province", &self.state),
+            ("locality", &self.locality),
+            ("organization", &self.organization),
+            ("organizationalUnit", &self.organizational_unit),
+        ] {
+            if let Some(value) = value {
+                subject.insert(key.to_owned(), json!(value));
+            }
+        }
+        subject.insert("commonName".to_owned(), json!(common_name));
+        Value::Object(subject)
+    }
+}
+
+impl fmt::Display for Subject {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .fields()
+            .into_iter()
+            .filter_map(|(_, key, value)| value.map(|value| format!("{key}={value}")))
+            .collect();
+        f.write_str(&fields.join(", "))
+    }
+}
+
+impl FromStr for Subject {
+    type Err = String;
+
+    /// Parse comma-separated `KEY=value` pairs with the keys `C`, `ST`, `L`,
+    /// `O` and `OU`. The common name is always the certificate's primary name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut subject = Self::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| format!("Invalid subject field '{pair}': expected KEY=value"))?;
+            if value.is_empty() {
+                return Err(format!("Subject field '{key}' is empty"));
+            }
+
+            let field = match key.to_ascii_uppercase().as_str() {
+                "C" => {
+                    if value.len() != 2 || !value.chars().all(|c| c.is_ascii_alphabetic()) {
+                        return Err(format!(
+                            "Invalid country '{value}': expected a two-letter code"
+                        ));
+                    }
+                    &mut subject.country
+                }
+                "ST" => &mut subject.state,
+                "L" => &mut subject.locality,
+                "O" => &mut subject.organization,
+                "OU" => &mut subject.organizational_unit,
+                "CN" => {
+                    return Err(
+                        "The subject common name is the server name and cannot be set".to_owned(),
+                    );
+                }
+                _ => {
+                    return Err(format!(
+                        "Unsupported subject field '{key}' (supported: C, ST, L, O, OU)"
+                    ));
+                }
+            };
+            if field.replace(value.to_owned()).is_some() {
+                return Err(format!("Subject field '{key}' is set twice"));
+            }
+        }
+        Ok(subject)
+    }
+}
+
+/// A certificate policy OID such as `2.23.140.1.2.1`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PolicyOid(Vec<u64>);
+
+impl PolicyOid {
+    /// Arcs of the OID.
+    #[must_use]
+    pub fn arcs(&self) -> &[u64] {
+        &self.0
+    }
+}
+
+impl fmt::Display for PolicyOid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let arcs: Vec<String> = self.0.iter().map(u64::to_string).collect();
+        f.write_str(&arcs.join("."))
+    }
+}
+
+impl FromStr for PolicyOid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let arcs = s
+            .split('.')
+            .map(|arc| {
+                if arc.is_empty() || !arc.chars().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                arc.parse::<u64>().ok()
+            })
+            .collect::<Option<Vec<u64>>>()
+            .ok_or_else(|| format!("Invalid policy OID '{s}': expected dotted decimal"))?;
+
+        match arcs.as_slice() {
+            [first, second, ..] if *first <= 1 && *second < 40 => Ok(Self(arcs)),
+            [2, second, ..] if *second <= u64::MAX - 80 => Ok(Self(arcs)),
+            _ => Err(format!(
+                "Invalid policy OID '{s}': expected at least two arcs starting with 0, 1 or 2"
+            )),
+        }
+    }
+}
+
+/// Parse a comma-separated list of policy OIDs. Blank entries are skipped.
+///
+/// # Errors
+///
+/// Returns error if any entry is not a valid OID.
+pub fn parse_policy_oids(list: &str) -> Result<Vec<PolicyOid>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|oid| !oid.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Everything a certificate carries besides its key, names and validity.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CertProfile {
+    /// Intended usage, which sets the key usage and extended key usage.
+    pub kind: ProfileKind,
+    /// Subject fields besides the common name.
+    pub subject: Subject,
+    /// Certificate policies to assert.
+    pub policy_oids: Vec<PolicyOid>,
+}
+
+impl CertProfile {
+    /// The default server profile with the historical subject for `service_name`.
+    #[must_use]
+    pub fn for_service(service_name: &str) -> Self {
+        Self {
+            kind: ProfileKind::default(),
+            subject: Subject::for_service(service_name),
+            policy_oids: Vec::new(),
+        }
+    }
+
+    /// Set the subject, usages, basic constraints and policies on `params`.
+    ///
+    /// Used both for self-signed certificates and for CSRs sent to a CA.
+    pub(crate) fn apply(&self, params: &mut CertificateParams, common_name: &str) {
+        params.distinguished_name = self.subject.distinguished_name(common_name);
+        params.is_ca = IsCa::ExplicitNoCa;
+        params.key_usages = self.kind.key_usages();
+        params.extended_key_usages = self.kind.extended_key_usages();
+        if !self.policy_oids.is_empty() {
+            params
+                .custom_extensions
+                .push(CustomExtension::from_oid_content(
+                    &CERTIFICATE_POLICIES,
+                    self.certificate_policies_der(),
+                ));
+        }
+    }
+
+    /// Template data for step-ca, readable from a provisioner template as
+    /// `.Insecure.User`.
+    #[must_use]
+    pub fn template_data(&self, common_name: &str) -> Value {
+        let policies: Vec<String> = self.policy_oids.iter().map(ToString::to_string).collect();
+        json!({
+            "subject": self.subject.step_template(common_name),
+            "keyUsage": self.kind.step_key_usages(),
+            "extKeyUsage": self.kind.step_extended_key_usages(),
+            "basicConstraints": { "isCA": false },
+            "policyIdentifiers": policies,
+        })
+    }
+
+    /// A complete certificate template for `step certificate create`.
+    #[must_use]
+    pub fn step_template(&self, common_name: &str, sans: &[SubjectAltName]) -> String {
+        let sans: Vec<Value> = sans
+            .iter()
+            .map(|san| {
+                let kind = match san {
+                    SubjectAltName::Dns(_) => "dns",
+                    SubjectAltName::Ip(_) => "ip",
+                    SubjectAltName::Uri(_) => "uri",
+                    SubjectAltName::Email(_) => "email",
+                };
+                json!({ "type": kind, "value": san.value() })
+            })
+            .collect();
+
+        let mut template = self.template_data(common_name);
+        if let Some(fields) = template.as_object_mut() {
+            fields.insert("sans".to_owned(), Value::Array(sans));
+        }
+        template.to_string()
+    }
+
+    /// DER `certificatePolicies` extension value asserting every policy OID
+    /// without qualifiers.
+    fn certificate_policies_der(&self) -> Vec<u8> {
+        let policies: Vec<u8> = self
+            .policy_oids
+            .iter()
+            .flat_map(|oid| der_tlv(0x30, &der_tlv(0x06, &oid_content(oid.arcs()))))
+            .collect();
+        der_tlv(0x30, &policies)
+    }
+}
+
+/// Content octets of a DER object identifier.
+fn oid_content(arcs: &[u64]) -> Vec<u8> {
+    let (first, rest) = match arcs {
+        [first, second, rest @ ..] => (first * 40 + second, rest),
+        _ => return Vec::new(),
+    };
+
+    let mut content = Vec::new();
+    for arc in std::iter::once(first).chain(rest.iter().copied()) {
+        let mut encoded = vec![u8::try_from(arc & 0x7f).unwrap_or_default()];
+        let mut remaining = arc >> 7;
+        while remaining > 0 {
+            encoded.push(u8::try_from(remaining & 0x7f).unwrap_or_default() | 0x80);
+            remaining >>= 7;
+        }
+        content.extend(encoded.iter().rev());
+    }
+    content
+}
+
+/// DER tag-length-value with a definite length.
+fn der_tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut tlv = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        tlv.push(u8::try_from(len).unwrap_or_default());
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        tlv.push(0x80 | u8::try_from(bytes.len()).unwrap_or_default());
+        tlv.extend(bytes);
+    }
+    tlv.extend_from_slice(content);
+    tlv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_kind_parsing() {
+        assert_eq!(
+            "server".parse::<ProfileKind>().unwrap(),
+            ProfileKind::Server
+        );
+        assert_eq!(
+            " Client ".parse::<ProfileKind>().unwrap(),
+            ProfileKind::Client
+        );
+        assert_eq!(
+            "server+client".parse::<ProfileKind>().unwrap(),
+            ProfileKind::ServerClient
+        );
+        assert_eq!(
+            "server-client".parse::<ProfileKind>().unwrap(),
+            ProfileKind::ServerClient
+        );
+        let err = "ca".parse::<ProfileKind>().unwrap_err();
+        assert!(err.contains("server, client, server-client"), "{err}");
+    }
+
+    #[test]
+    fn test_subject_parsing() {
+        let subject: Subject = "C=us, O=Acme Corp, OU=Platform, L=Seattle, ST=WA"
+            .parse()
+            .unwrap();
+        assert_eq!(subject.country.as_deref(), Some("us"));
+        assert_eq!(subject.organization.as_deref(), Some("Acme Corp"));
+        assert_eq!(subject.organizational_unit.as_deref(), Some("Platform"));
+        assert_eq!(
+            subject.to_string(),
+            "C=us, ST=WA, L=Seattle, O=Acme Corp, OU=Platform"
+        );
+        assert_eq!("".parse::<Subject>().unwrap(), Subject::default());
+
+        for invalid in ["C=Korea", "O=", "CN=api", "X=1", "O=a,O=b", "Acme"] {
+            assert!(invalid.parse::<Subject>().is_err(), "accepted {invalid}");
+        }
+    }
+
+    #[test]
+    fn test_policy_oid_parsing() {
+        let oids = parse_policy_oids("2.23.140.1.2.1, 1.3.6.1.4.1.99999.1,").unwrap();
+        assert_eq!(oids.len(), 2);
+        assert_eq!(oids[0].to_string(), "2.23.140.1.2.1");
+
+        for invalid in ["1", "3.1", "1.40", "1..2", "1.2.a", "-1.2"] {
+            assert!(invalid.parse::<PolicyOid>().is_err(), "accepted {invalid}");
+        }
+    }
+
+    #[test]
+    fn test_certificate_policies_der() {
+        let profile = CertProfile {
+            policy_oids: parse_policy_oids("2.23.140.1.2.1").unwrap(),
+            ..CertProfile::for_service("api")
+        };
+        assert_eq!(
+            profile.certificate_policies_der(),
+            [
+                0x30, 0x0a, 0x30, 0x08, 0x06, 0x06, 0x67, 0x81, 0x0c, 0x01, 0x02, 0x01
+            ]
+        );
+        assert_eq!(der_tlv(0x04, &[0; 200])[..3], [0x04, 0x81, 200]);
+    }
+
+    #[test]
+    fn test_step_template() {
+        let profile = CertProfile {
+            kind: ProfileKind::ServerClient,
+            subject: "O=Acme".parse().unwrap(),
+            policy_oids: parse_policy_oids("1.2.3").unwrap(),
+        };
+        let template: Value = serde_json::from_str(&profile.step_template(
+            "api.example.com",
+            &[
+                SubjectAltName::Dns("api.example.com".to_owned()),
+                SubjectAltName::Ip("::1".parse().unwrap()),
+            ],
+        ))
+        .unwrap();
+
+        assert_eq!(
+            template,
+            json!({
+                "subject": { "organization": "Acme", "commonName": "api.example.com" },
+                "sans": [
+                    { "type": "dns", "value": "api.example.com" },
+                    { "type": "ip", "value": "::1" },
+                ],
+                "keyUsage": ["digitalSignature", "keyEncipherment"],
+                "extKeyUsage": ["serverAuth", "clientAuth"],
+                "basicConstraints": { "isCA": false },
+                "policyIdentifiers": ["1.2.3"],
+            })
+        );
+    }
+}
@@ -0,0 +1,424 @@
+//! Subject alternative names.
+
+use rcgen::{SanType, string::Ia5String};
+use std::{fmt, net::IpAddr, str::FromStr};
+
+/// Longest DNS name allowed by RFC 1035.
+const MAX_DNS_NAME_LEN: usize = 253;
+
+/// Longest DNS label allowed by RFC 1035.
+const MAX_DNS_LABEL_LEN: usize = 63;
+
+/// A subject alternative name entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum SubjectAltName {
+    /// DNS host name.
+    Dns(String),
+    /// IPv4 or IPv6 address.
+    Ip(IpAddr),
+    /// Uniform resource identifier.
+    Uri(String),
+    /// RFC 822 email address.
+    Email(String),
+}
+
+impl SubjectAltName {
+    /// SAN for a host given either as an IP address or a DNS name.
+    #[must_use]
+    pub fn from_host(host: &str) -> Self {
+        host.parse::<IpAddr>()
+            .map_or_else(|_| Self::Dns(host.to_owned()), Self::Ip)
+    }
+
+    /// The name without its type prefix, as accepted by `step --san`.
+    #[must_use]
+    pub fn value(&self) -> String {
+        match self {
+            Self::Dns(name) => name.clone(),
+            Self::Ip(addr) => addr.to_string(),
+            Self::Uri(uri) => uri.clone(),
+            Self::Email(email) => email.clone(),
+        }
+    }
+
+    /// Check that a CA can put this name in a certificate.
+    ///
+    /// DNS names must be ASCII (IDNs in their `xn--` form) with valid labels;
+    /// a wildcard is only allowed as the whole leftmost label of a name with
+    /// at least two more labels. URIs need a scheme and email addresses a
+    /// local part and a valid domain.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the first problem found.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            Self::Dns(name) => validate_dns_name(name, true),
+            Self::Ip(_) => Ok(()),
+            Self::Uri(uri) => validate_uri(uri),
+            Self::Email(email) => validate_email(email),
+        }
+        .map_err(|reason| format!("Invalid SAN '{self}': {reason}"))
+    }
+
+    /// Convert to the rcgen representation used for issuing certificates.
+    pub(crate) fn to_san_type(&self) -> Result<SanType, rcgen::Error> {
+        Ok(match self {
+            Self::Dns(name) => SanType::DnsName(Ia5String::try_from(name.as_str())?),
+            Self::Ip(addr) => SanType::IpAddress(*addr),
+            Self::Uri(uri) => SanType::URI(Ia5String::try_from(uri.as_str())?),
+            Self::Email(email) => SanType::Rfc822Name(Ia5String::try_from(email.as_str())?),
+        })
+    }
+}
+
+impl FromStr for SubjectAltName {
+    type Err = String;
+
+    /// Parse and validate one entry.
+    ///
+    /// Entries may carry a `DNS:`, `IP:`, `URI:` or `email:` prefix. Without
+    /// one, a value containing `://` is a URI, one containing `@` is an email
+    /// address, and anything else is an IP address or a DNS name. IPv6
+    /// addresses may be written in brackets.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let san = match s.split_once(':') {
+            Some((prefix, value)) if prefix.eq_ignore_ascii_case("DNS") => {
+                Self::Dns(value.to_owned())
+            }
+            Some((prefix, value)) if prefix.eq_ignore_ascii_case("IP") => Self::Ip(
+                unbracket(value)
+                    .parse()
+                    .map_err(|_| format!("Invalid SAN '{s}': not an IP address"))?,
+            ),
+            Some((prefix, value)) if prefix.eq_ignore_ascii_case("URI") => {
+                Self::Uri(value.to_owned())
+            }
+            Some((prefix, value)) if prefix.eq_ignore_ascii_case("email") => {
+                Self::Email(value.to_owned())
+            }
+            _ if s.contains("://") => Self::Uri(s.to_owned()),
+            _ if s.contains('@') => Self::Email(s.to_owned()),
+            _ => Self::from_host(unbracket(s)),
+        };
+        san.validate()?;
+        Ok(san)
+    }
+}
+
+/// Parse a comma-separated list of SAN entries, such as `CERT_DOMAINS`.
+/// Blank entries are skipped.
+///
+/// # Errors
+///
+/// Returns error if any entry is not a valid SAN.
+pub fn parse_list(list: &str) -> Result<Vec<SubjectAltName>, String> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Parse the extra names added to every certificate. `none` disables them.
+///
+/// # Errors
+///
+/// Returns error if any entry is not a valid SAN.
+pub fn parse_extra_list(list: &str) -> Result<Vec<SubjectAltName>, String> {
+    if list.trim().eq_ignore_ascii_case("none") {
+        Ok(Vec::new())
+    } else {
+        parse_list(list)
+    }
+}
+
+/// Names added to every certificate unless configured otherwise:
+/// `localhost` and `127.0.0.1`.
+#[must_use]
+pub fn default_extra_sans() -> Vec<SubjectAltName> {
+    vec![
+        SubjectAltName::Dns("localhost".to_owned()),
+        SubjectAltName::Ip(IpAddr::from([127, 0, 0, 1])),
+    ]
+}
+
+/// Strip the brackets from an IPv6 literal such as `[::1]`.
+fn unbracket(host: &str) -> &str {
+    host.strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host)
+}
+
+fn validate_dns_name(name: &str, allow_wildcard: bool) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("empty DNS name".to_owned());
+    }
+    if !name.is_ascii() {
+        return Err("DNS names must be ASCII, use the xn-- form for IDNs".to_owned());
+    }
+    if name.len() > MAX_DNS_NAME_LEN {
+        return Err(format!("longer than {MAX_DNS_NAME_LEN} characters"));
+    }
+
+    let labels: Vec<&str> = name.split('.').collect();
+    for (position, label) in labels.iter().enumerate() {
+        if *label == "*" {
+            if !allow_wildcard || position > 0 {
+                return Err("wildcard is only allowed as the leftmost label".to_owned());
+            }
+            if labels.len() < 3 {
+                return Err("wildcard needs at least two labels after it".to_owned());
+            }
+            continue;
+        }
+        validate_dns_label(label)?;
+    }
+    Ok(())
+}
+
+fn validate_dns_label(label: &str) -> Result<(), String> {
+    if label.is_empty() {
+        return Err("empty label".to_owned());
+    }
+    if label.len() > MAX_DNS_LABEL_LEN {
+        return Err(format!(
+            "label '{label}' is longer than {MAX_DNS_LABEL_LEN} characters"
+        ));
+    }
+    if label.contains('*') {
+        return Err(format!("label '{label}' has a partial wildcard"));
+    }
+    if !label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("label '{label}' has invalid characters"));
+    }
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err(format!("label '{label}' starts or ends with a hyphen"));
+    }
+    Ok(())
+}
+
+fn validate_uri(uri: &str) -> Result<(), String> {
+    if !uri.is_ascii()
+        || uri
+            .chars()
+            .any(|c| c.is_ascii_whitespace() || c.is_ascii_control())
+    {
+        return Err("URIs must be ASCII without spaces".to_owned());
+    }
+    let Some((scheme, rest)) = uri.split_once(':') else {
+        return Err("missing scheme".to_owned());
+    };
+    let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    if !valid_scheme {
+        return Err(format!("invalid scheme '{scheme}'"));
+    }
+    if rest.is_empty() {
+        return Err("nothing after the scheme".to_owned());
+    }
+    Ok(())
+}
+
+fn validate_email(email: &str) -> Result<(), String> {
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return Err("missing '@'".to_owned());
+    };
+    if local.is_empty()
+        || !local.is_ascii()
+        || local
+            .chars()
+            .any(|c| c.is_ascii_whitespace() || c.is_ascii_control() || c == '@')
+    {
+        return Err("invalid local part".to_owned());
+    }
+    validate_dns_name(domain, false)
+}
+
+impl fmt::Display for SubjectAltName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Dns(name) => write!(f, "DNS:{name}"),
+            Self::Ip(addr) => write!(f, "IP:{addr}"),
+            Self::Uri(uri) => write!(f, "URI:{uri}"),
+            Self::Email(email) => write!(f, "email:{email}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_ip(host: &str) -> bool {
+        matches!(SubjectAltName::from_host(host), SubjectAltName::Ip(_))
+    }
+
+    #[test]
+    fn test_from_host_valid_ipv4() {
+        assert!(is_ip("192.168.1.1"));
+        assert!(is_ip("127.0.0.1"));
+        assert!(is_ip("10.0.0.1"));
+        assert!(is_ip("172.16.0.1"));
+        assert!(is_ip("8.8.8.8"));
+        assert!(is_ip("255.255.255.255"));
+        assert!(is_ip("0.0.0.0"));
+    }
+
+    #[test]
+    fn test_from_host_valid_ipv6() {
+        assert!(is_ip("::1"));
+        assert!(is_ip("2001:db8::1"));
+        assert!(is_ip("fe80::1"));
+        assert!(is_ip("::"));
+        assert!(is_ip("2001:0db8:85a3:0000:0000:8a2e:0370:7334"));
+    }
+
+    #[test]
+    fn test_from_host_domain_names() {
+        assert!(!is_ip("example.com"));
+        assert!(!is_ip("subdomain.example.com"));
+        assert!(!is_ip("api.company.internal"));
+        assert!(!is_ip("localhost"));
+        assert!(!is_ip("my-service"));
+        assert!(!is_ip("test1.example.com"));
+        assert!(!is_ip("web-server.local"));
+    }
+
+    #[test]
+    fn test_from_host_invalid_ip_formats_are_not_ips() {
+        assert!(!is_ip(""));
+        assert!(!is_ip("256.256.256.256")); // Invalid IPv4
+        assert!(!is_ip("192.168.1")); // Incomplete IPv4
+        assert!(!is_ip("192.168.1.1.1")); // Too many octets
+        assert!(!is_ip("not-an-ip"));
+        assert!(!is_ip("192.168.abc.1"));
+        assert!(!is_ip("::1::2")); // Invalid IPv6
+    }
+
+    #[test]
+    fn test_parse_list() {
+        assert_eq!(
+            parse_list(" api.company.com, web.company.com,,10.0.0.5 ,").unwrap(),
+            vec![
+                SubjectAltName::Dns("api.company.com".to_owned()),
+                SubjectAltName::Dns("web.company.com".to_owned()),
+                SubjectAltName::Ip("10.0.0.5".parse().unwrap()),
+            ]
+        );
+        assert!(parse_list(" , ").unwrap().is_empty());
+        assert!(parse_list("api.company.com,bad_name").is_err());
+    }
+
+    #[test]
+    fn test_parse_extra_list() {
+        assert_eq!(
+            parse_extra_list("localhost, 127.0.0.1").unwrap(),
+            default_extra_sans()
+        );
+        assert!(parse_extra_list("none").unwrap().is_empty());
+        assert!(parse_extra_list(" None ").unwrap().is_empty());
+        assert!(parse_extra_list("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_san_types() {
+        let parse = |s: &str| s.parse::<SubjectAltName>().unwrap();
+
+        assert_eq!(
+            parse("*.example.com"),
+            SubjectAltName::Dns("*.example.com".to_owned())
+        );
+        assert_eq!(
+            parse("DNS:api.example.com"),
+            SubjectAltName::Dns("api.example.com".to_owned())
+        );
+        assert_eq!(
+            parse("[2001:db8::1]"),
+            SubjectAltName::Ip("2001:db8::1".parse().unwrap())
+        );
+        assert_eq!(parse("IP:::1"), SubjectAltName::Ip("::1".parse().unwrap()));
+        assert_eq!(
+            parse("ip:[fe80::1]"),
+            SubjectAltName::Ip("fe80::1".parse().unwrap())
+        );
+        assert_eq!(
+            parse("spiffe://example.com/api"),
+            SubjectAltName::Uri("spiffe://example.com/api".to_owned())
+        );
+        assert_eq!(
+            parse("URI:urn:uuid:6e8bc430-9c3a-11d9-9669-0800200c9a66"),
+            SubjectAltName::Uri("urn:uuid:6e8bc430-9c3a-11d9-9669-0800200c9a66".to_owned())
+        );
+        assert_eq!(
+            parse("ops@example.com"),
+            SubjectAltName::Email("ops@example.com".to_owned())
+        );
+        assert_eq!(
+            parse("email:ops@example.com"),
+            SubjectAltName::Email("ops@example.com".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_invalid_sans_are_rejected() {
+        for entry in [
+            "IP:not-an-ip",
+            "*",
+            "*.com",
+            "api.*.example.com",
+            "api*.example.com",
+            "bücher.example",
+            "-api.example.com",
+            "api-.example.com",
+            "api..example.com",
+            "under_score.example.com",
+            "api.example.com.",
+            &format!("{}.example.com", "a".repeat(64)),
+            "URI:no-scheme",
+            "URI:1http://example.com",
+            "URI:https://example.com/a b",
+            "URI:https:",
+            "email:ops",
+            "@example.com",
+            "ops@*.example.com",
+            "ops@example..com",
+        ] {
+            assert!(entry.parse::<SubjectAltName>().is_err(), "accepted {entry}");
+        }
+    }
+
+    #[test]
+    fn test_valid_edge_cases_are_accepted() {
+        for entry in [
+            "xn--bcher-kva.example",
+            "my-service",
+            "localhost",
+            &format!("{}.example.com", "a".repeat(63)),
+            "*.api.example.com",
+        ] {
+            assert!(entry.parse::<SubjectAltName>().is_ok(), "rejected {entry}");
+        }
+    }
+
+    #[test]
+    fn test_display_uses_openssl_prefixes() {
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        assert_eq!(
+            SubjectAltName::Dns("a.example.com".to_owned()).to_string(),
+            "DNS:a.example.com"
+        );
+        assert_eq!(SubjectAltName::Ip(ip).to_string(), "IP:10.0.0.1");
+        assert_eq!(
+            SubjectAltName::Uri("spiffe://example.com/api".to_owned()).to_string(),
+            "URI:spiffe://example.com/api"
+        );
+        assert_eq!(
+            SubjectAltName::Email("ops@example.com".to_owned()).to_string(),
+            "email:ops@example.com"
+        );
+    }
+}
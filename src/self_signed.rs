@@ -0,0 +1,231 @@
+//! In-process self-signed certificate generation.
+//!
+//! This is the fallback used when no CA is available. Keys and certificates
+//! are produced natively, so it works in images without an `openssl` binary.
+
+use crate::{key::KeyType, profile::CertProfile, san::SubjectAltName};
+use chrono::{DateTime, Duration, Utc};
+use rcgen::{CertificateParams, SerialNumber};
+use time::OffsetDateTime;
+
+/// A freshly generated certificate and its private key.
+#[derive(Debug, Clone)]
+pub struct GeneratedCert {
+    /// PEM encoded certificate.
+    pub cert_pem: String,
+    /// PEM encoded PKCS#8 private key.
+    pub key_pem: String,
+}
+
+/// Parameters for a self-signed certificate.
+#[derive(Debug, Clone)]
+pub struct SelfSignedRequest {
+    /// Subject fields, usages and policies.
+    pub profile: CertProfile,
+    /// Subject common name.
+    pub common_name: String,
+    /// Subject alternative names.
+    pub subject_alt_names: Vec<SubjectAltName>,
+    /// Validity period, starting now.
+    pub validity: Duration,
+    /// Algorithm of the generated key.
+    pub key_type: KeyType,
+}
+
+/// Generate a key and a self-signed certificate for it.
+///
+/// # Errors
+///
+/// Returns error if a SAN is not representable, or if key generation or
+/// signing fails.
+pub fn generate(request: &SelfSignedRequest) -> Result<GeneratedCert, rcgen::Error> {
+    generate_at(request, Utc::now())
+}
+
+fn generate_at(
+    request: &SelfSignedRequest,
+    now: DateTime<Utc>,
+) -> Result<GeneratedCert, rcgen::Error> {
+    let mut params = CertificateParams::default();
+    request.profile.apply(&mut params, &request.common_name);
+
+    params.subject_alt_names = request
+        .subject_alt_names
+        .iter()
+        .map(SubjectAltName::to_san_type)
+        .collect::<Result<_, _>>()?;
+
+    params.not_before = to_offset(now)?;
+    params.not_after = to_offset(now + request.validity)?;
+    params.serial_number = Some(random_serial()?);
+
+    let key = request.key_type.generate()?;
+    let cert = params.self_signed(&key)?;
+
+    Ok(GeneratedCert {
+        cert_pem: cert.pem(),
+        key_pem: key.serialize_pem(),
+    })
+}
+
+fn to_offset(time: DateTime<Utc>) -> Result<OffsetDateTime, rcgen::Error> {
+    OffsetDateTime::from_unix_timestamp(time.timestamp()).map_err(|_| rcgen::Error::Time)
+}
+
+/// Random positive 128-bit serial number.
+fn random_serial() -> Result<SerialNumber, rcgen::Error> {
+    let mut bytes = [0u8; 16];
+    aws_lc_rs::rand::fill(&mut bytes).map_err(|_| rcgen::Error::RingUnspecified)?;
+    if let Some(first) = bytes.first_mut() {
+        *first &= 0x7f;
+    }
+    Ok(SerialNumber::from_slice(&bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{KeyAlgorithm, certificate};
+
+    fn sans() -> Vec<SubjectAltName> {
+        vec![
+            SubjectAltName::Dns("localhost".to_owned()),
+            SubjectAltName::Ip("127.0.0.1".parse().unwrap()),
+            SubjectAltName::Dns("api.example.com".to_owned()),
+        ]
+    }
+
+    #[test]
+    fn test_generate_self_signed_certificate() {
+        let request = SelfSignedRequest {
+            profile: CertProfile::for_service("api"),
+            common_name: "api.example.com".to_owned(),
+            subject_alt_names: sans(),
+            validity: Duration::days(15),
+            key_type: KeyType::Rsa2048,
+        };
+        let now = Utc::now();
+
+        let generated = generate_at(&request, now).unwrap();
+        let info = certificate::parse_certificate_info(generated.cert_pem.as_bytes()).unwrap();
+
+        assert_eq!(info.subject, "C=KR, O=api Service, CN=api.example.com");
+        assert_eq!(info.issuer, info.subject);
+        assert_eq!(info.subject_alt_names, request.subject_alt_names);
+        assert_eq!(info.key_algorithm, KeyAlgorithm::Rsa);
+        assert_eq!(info.key_bits, 2048);
+        assert_eq!(info.signature_algorithm, "sha256WithRSAEncryption");
+        assert_eq!(info.validity.not_before.timestamp(), now.timestamp());
+        assert_eq!(
+            info.validity.not_after.timestamp(),
+            (now + Duration::days(15)).timestamp()
+        );
+        assert!(
+            certificate::key_matches(generated.cert_pem.as_bytes(), generated.key_pem.as_bytes())
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_generate_applies_profile() {
+        use crate::profile::{ProfileKind, parse_policy_oids};
+        use x509_parser::{extensions::ParsedExtension, pem::parse_x509_pem};
+
+        let request = SelfSignedRequest {
+            profile: CertProfile {
+                kind: ProfileKind::Client,
+                subject: "C=US, O=Acme, OU=Platform".parse().unwrap(),
+                policy_oids: parse_policy_oids("2.23.140.1.2.1,1.3.6.1.4.1.99999.1").unwrap(),
+            },
+            common_name: "api.example.com".to_owned(),
+            subject_alt_names: sans(),
+            validity: Duration::days(1),
+            key_type: KeyType::default(),
+        };
+
+        let generated = generate(&request).unwrap();
+        let info = certificate::parse_certificate_info(generated.cert_pem.as_bytes()).unwrap();
+        assert_eq!(
+            info.subject,
+            "C=US, O=Acme, OU=Platform, CN=api.example.com"
+        );
+
+        let (_, pem) = parse_x509_pem(generated.cert_pem.as_bytes()).unwrap();
+        let cert = pem.parse_x509().unwrap();
+        let key_usage = cert.key_usage().unwrap().unwrap().value;
+        assert!(key_usage.digital_signature() && !key_usage.key_encipherment());
+        let eku = cert.extended_key_usage().unwrap().unwrap().value;
+        assert!(eku.client_auth && !eku.server_auth);
+        assert!(!cert.basic_constraints().unwrap().unwrap().value.ca);
+
+        let policies: Vec<String> = cert
+            .iter_extensions()
+            .find_map(|ext| match ext.parsed_extension() {
+                ParsedExtension::CertificatePolicies(policies) => Some(policies),
+                _ => None,
+            })
+            .unwrap()
+            .iter()
+            .map(|policy| policy.policy_id.to_id_string())
+            .collect();
+        assert_eq!(policies, ["2.23.140.1.2.1", "1.3.6.1.4.1.99999.1"]);
+    }
+
+    #[test]
+    fn test_generate_honors_key_type() {
+        let cases = [
+            (KeyType::EcdsaP256, "ecdsa-with-SHA256"),
+            (KeyType::EcdsaP384, "ecdsa-with-SHA384"),
+            (KeyType::Ed25519, "ed25519"),
+            (KeyType::Rsa3072, "sha256WithRSAEncryption"),
+        ];
+
+        for (key_type, signature_algorithm) in cases {
+            let request = SelfSignedRequest {
+                profile: CertProfile::for_service("api"),
+                common_name: "api.example.com".to_owned(),
+                subject_alt_names: sans(),
+                validity: Duration::days(1),
+                key_type,
+            };
+
+            let generated = generate(&request).unwrap();
+            let info = certificate::parse_certificate_info(generated.cert_pem.as_bytes()).unwrap();
+            assert!(
+                key_type.matches(&info.key_algorithm, info.key_bits),
+                "{key_type}"
+            );
+            assert_eq!(info.signature_algorithm, signature_algorithm);
+        }
+    }
+
+    #[test]
+    fn test_generate_uses_unique_serials() {
+        let request = SelfSignedRequest {
+            profile: CertProfile::for_service("api"),
+            common_name: "api.example.com".to_owned(),
+            subject_alt_names: sans(),
+            validity: Duration::days(1),
+            key_type: KeyType::default(),
+        };
+
+        let first = generate(&request).unwrap();
+        let second = generate(&request).unwrap();
+        let first = certificate::parse_certificate_info(first.cert_pem.as_bytes()).unwrap();
+        let second = certificate::parse_certificate_info(second.cert_pem.as_bytes()).unwrap();
+        assert_ne!(first.serial, second.serial);
+    }
+
+    #[test]
+    fn test_generate_rejects_non_ascii_dns_name() {
+        let request = SelfSignedRequest {
+            profile: CertProfile::for_service("api"),
+            common_name: "bücher.example".to_owned(),
+            subject_alt_names: vec![SubjectAltName::Dns("bücher.example".to_owned())],
+            validity: Duration::days(1),
+            key_type: KeyType::default(),
+        };
+
+        assert!(generate(&request).is_err());
+    }
+}
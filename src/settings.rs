@@ -0,0 +1,727 @@
+//! Layered configuration sources.
+//!
+//! Every setting has the name of its environment variable. A TOML file is
+//! flattened into the same names (`[step_ca] url` becomes `STEP_CA_URL`,
+//! `[[certificates]]` entries become `CERTIFICATES` and `NAME_*`, and
+//! `[[notifiers]]` entries `NOTIFIERS` and `NOTIFIER_NAME_*`), then the
+//! environment and command-line flags are layered on top of it.
+
+use crate::{
+    CertSpec, Config, duration,
+    notifier::{self, EmailSpec, NotifierSettings, WebhookSpec},
+    san::SubjectAltName,
+};
+use chrono::Duration;
+use std::{collections::BTreeMap, env, fmt, str::FromStr};
+use toml::{Table, Value};
+
+/// Environment variable naming the configuration file when `--config` is not given.
+pub const CONFIG_FILE_VAR: &str = "DIMENSION_BRIDGE_CONFIG";
+
+/// Settings that can be given on the command line as `--kebab-case` flags.
+pub const KNOWN_SETTINGS: &[&str] = &[
+    "SERVER_IP",
+    "CERT_DOMAINS",
+    "EXTRA_SANS",
+    "CERTIFICATES",
+    "SERVICE_NAME",
+    "CERT_DIR",
+    "LOG_DIR",
+    "CHECK_INTERVAL",
+    "DAYS_BEFORE_RENEWAL",
+    "RENEW_AT_PERCENT",
+    "CERT_VALIDITY_DAYS",
+    "KEY_TYPE",
+    "ISSUERS",
+    "FALLBACK_POLICY",
+    "CERT_PROFILE",
+    "CERT_SUBJECT",
+    "CERT_POLICY_OIDS",
+    "RELOAD_COMMAND",
+    "SLACK_WEBHOOK_URL",
+    "NOTIFIERS",
+    "EXPIRY_WARNINGS",
+    "DEDUP_WINDOWS",
+    "NOTIFY_RATE_LIMIT",
+    "ESCALATE_AFTER",
+    "MAX_PARALLEL_RENEWALS",
+    "STEP_CA_URL",
+    "STEP_CA_FINGERPRINT",
+    "STEP_CA_ROOT",
+    "STEP_CA_PROVISIONER",
+    "STEP_CA_PROVISIONER_PASSWORD_FILE",
+    "STEP_CA_PROVISIONER_KEY_FILE",
+    "STEP_CA_TOKEN_FILE",
+];
+
+/// Shown instead of secrets in the effective configuration.
+const REDACTED: &str = "<redacted>";
+
+/// Setting values by environment variable name.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Settings {
+    values: BTreeMap<String, String>,
+}
+
+impl Settings {
+    /// Settings from the environment only.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let mut settings = Self::default();
+        settings.extend_from_env();
+        settings
+    }
+
+    /// Settings from the TOML configuration file text.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the text is not valid TOML or holds a value that
+    /// has no setting, such as an array of tables other than `certificates`
+    /// and `notifiers`.
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let table: Table = text.parse().map_err(|e: toml::de::Error| e.to_string())?;
+        let mut settings = Self::default();
+        settings.flatten("", &table)?;
+        Ok(settings)
+    }
+
+    /// Load the file given with `--config`, or named by `DIMENSION_BRIDGE_CONFIG`,
+    /// then layer the environment and `overrides` on top of it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the configuration file cannot be read or
+    /// parsed.
+    pub fn load(
+        config_file: Option<&str>,
+        overrides: &[(String, String)],
+    ) -> Result<Self, ConfigError> {
+        let config_file = config_file
+            .map(str::to_owned)
+            .or_else(|| env::var(CONFIG_FILE_VAR).ok())
+            .filter(|path| !path.trim().is_empty());
+
+        let mut settings = match config_file {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read config file {path}: {e}"))?;
+                Self::from_toml(&text).map_err(|e| format!("Invalid config file {path}: {e}"))?
+            }
+            None => Self::default(),
+        };
+
+        settings.extend_from_env();
+        for (name, value) in overrides {
+            settings.set(name, value);
+        }
+        Ok(settings)
+    }
+
+    /// Value of a setting.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Set a setting, replacing any earlier value.
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.values.insert(name.to_owned(), value.to_owned());
+    }
+
+    fn extend_from_env(&mut self) {
+        self.values.extend(env::vars());
+    }
+
+    fn flatten(&mut self, prefix: &str, table: &Table) -> Result<(), String> {
+        for (key, value) in table {
+            let name = format!("{prefix}{}", setting_name(key));
+            match value {
+                Value::Table(table) => self.flatten(&format!("{name}_"), table)?,
+                Value::Array(entries) if name == "CERTIFICATES" => {
+                    self.named_entries("certificates", entries, env_prefix)?;
+                }
+                Value::Array(entries) if name == "NOTIFIERS" => {
+                    self.named_entries("notifiers", entries, notifier::prefix)?;
+                }
+                Value::Array(entries) => {
+                    let values = entries
+                        .iter()
+                        .map(|entry| scalar(&name, entry))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    // Header values may hold commas, so headers go one per line
+                    let separator = if name.ends_with("_HEADERS") {
+                        "\n"
+                    } else {
+                        ","
+                    };
+                    self.values.insert(name, values.join(separator));
+                }
+                value => {
+                    let value = scalar(&name, value)?;
+                    self.values.insert(name, value);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `[[key]]` entries, e.g. `[[certificates]]`: the names go to the `KEY`
+    /// list and the other keys to `{prefix(name)}*`.
+    fn named_entries(
+        &mut self,
+        key: &str,
+        entries: &[Value],
+        prefix: fn(&str) -> String,
+    ) -> Result<(), String> {
+        let mut names = Vec::new();
+        for entry in entries {
+            let Value::Table(table) = entry else {
+                return Err(format!("{key} must be an array of tables"));
+            };
+            let Some(Value::String(name)) = table.get("name") else {
+                return Err(format!("every [[{key}]] entry requires a name"));
+            };
+
+            let mut settings = table.clone();
+            settings.remove("name");
+            self.flatten(&prefix(name), &settings)?;
+            names.push(name.clone());
+        }
+        self.values.insert(setting_name(key), names.join(","));
+        Ok(())
+    }
+}
+
+/// Every problem found in a configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    /// One message per problem, naming the setting and its value.
+    pub errors: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Invalid configuration:")?;
+        for error in &self.errors {
+            write!(f, "\n  - {error}")?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<String> for ConfigError {
+    fn from(error: String) -> Self {
+        Self {
+            errors: vec![error],
+        }
+    }
+}
+
+impl From<&str> for ConfigError {
+    fn from(error: &str) -> Self {
+        error.to_owned().into()
+    }
+}
+
+/// Collects the problems found while loading a configuration, so they are
+/// all reported at once instead of one per attempt.
+#[derive(Debug, Default)]
+pub(crate) struct Validation {
+    errors: Vec<String>,
+}
+
+impl Validation {
+    /// Record a problem, once.
+    pub(crate) fn error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        if !self.errors.contains(&message) {
+            self.errors.push(message);
+        }
+    }
+
+    /// Record that setting `name` has an unusable `value`.
+    pub(crate) fn invalid(&mut self, name: &str, value: &str, reason: impl fmt::Display) {
+        self.error(format!("{name}={value:?}: {reason}"));
+    }
+
+    /// Parse setting `name`, or return `default` when it is not set or malformed.
+    pub(crate) fn parse<T>(&mut self, settings: &Settings, name: &str, default: T) -> T
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.parse_with(settings, name, |value| value.trim().parse::<T>())
+            .unwrap_or(default)
+    }
+
+    /// Parse setting `name` as a whole number, or return `default` when it is
+    /// not set or malformed.
+    pub(crate) fn number<T: FromStr>(&mut self, settings: &Settings, name: &str, default: T) -> T {
+        self.parse_with(settings, name, |value| {
+            value
+                .trim()
+                .parse::<T>()
+                .map_err(|_| "expected a whole number")
+        })
+        .unwrap_or(default)
+    }
+
+    /// Parse setting `name` as a duration, where a bare number counts in
+    /// `unit`, or return `default` when it is not set or malformed.
+    pub(crate) fn duration(
+        &mut self,
+        settings: &Settings,
+        name: &str,
+        unit: Duration,
+        default: Duration,
+    ) -> Duration {
+        self.parse_with(settings, name, |value| duration::parse(value, unit))
+            .unwrap_or(default)
+    }
+
+    /// Convert setting `name` with `parse`; `None` when it is not set or malformed.
+    pub(crate) fn parse_with<T, E: fmt::Display>(
+        &mut self,
+        settings: &Settings,
+        name: &str,
+        parse: impl FnOnce(&str) -> Result<T, E>,
+    ) -> Option<T> {
+        let value = settings.get(name)?;
+        match parse(value) {
+            Ok(parsed) => Some(parsed),
+            Err(e) => {
+                self.invalid(name, value, e);
+                None
+            }
+        }
+    }
+
+    /// Whether a problem was recorded for setting `name`.
+    pub(crate) fn reported(&self, name: &str) -> bool {
+        let prefix = format!("{name}=");
+        self.errors.iter().any(|error| error.starts_with(&prefix))
+    }
+
+    /// Fail with every recorded problem, if any.
+    pub(crate) fn finish(self) -> Result<(), ConfigError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError {
+                errors: self.errors,
+            })
+        }
+    }
+}
+
+/// Prefix of the settings of certificate `name`: the name in upper case with
+/// `-` and `.` replaced by `_`, followed by `_`.
+#[must_use]
+pub fn env_prefix(name: &str) -> String {
+    format!("{}_", name.to_ascii_uppercase().replace(['-', '.'], "_"))
+}
+
+/// Setting name of a file key or command-line flag: `cert-dir` and
+/// `cert_dir` are both `CERT_DIR`.
+#[must_use]
+pub fn setting_name(key: &str) -> String {
+    key.to_ascii_uppercase().replace('-', "_")
+}
+
+fn scalar(name: &str, value: &Value) -> Result<String, String> {
+    match value {
+        Value::String(value) => Ok(value.clone()),
+        Value::Integer(value) => Ok(value.to_string()),
+        Value::Float(value) => Ok(value.to_string()),
+        Value::Boolean(value) => Ok(value.to_string()),
+        Value::Datetime(value) => Ok(value.to_string()),
+        Value::Array(_) | Value::Table(_) => Err(format!("{name} must be a plain value or a list")),
+    }
+}
+
+/// The effective configuration in the configuration file format, with
+/// secrets redacted.
+#[must_use]
+pub fn to_toml(config: &Config) -> String {
+    let mut table = Table::new();
+    // The top-level certificate, which are the defaults of the listed ones
+    insert_certificate(
+        &mut table,
+        &CertSpec {
+            name: config.service_name.clone(),
+            server_ip: config.server_ip.clone(),
+            subject_alt_names: config.subject_alt_names.clone(),
+            cert_dir: config.cert_dir.clone(),
+            cert_validity: config.cert_validity,
+            renew_before: config.renew_before,
+            renew_at_percent: config.renew_at_percent,
+            reload_command: config.reload_command.clone(),
+            profile: config.profile.clone(),
+        },
+    );
+    table.insert(
+        "extra_sans".to_owned(),
+        sans(&config.extra_subject_alt_names),
+    );
+    table.insert(
+        "service_name".to_owned(),
+        config.service_name.clone().into(),
+    );
+    table.insert("log_dir".to_owned(), config.log_dir.clone().into());
+    table.insert(
+        "check_interval".to_owned(),
+        duration::format(config.check_interval).into(),
+    );
+    if let Some(key_type) = config.key_type {
+        table.insert("key_type".to_owned(), key_type.to_string().into());
+    }
+    table.insert(
+        "issuers".to_owned(),
+        Value::Array(
+            config
+                .issuer_chain()
+                .iter()
+                .map(|kind| kind.as_str().into())
+                .collect(),
+        ),
+    );
+    table.insert(
+        "fallback_policy".to_owned(),
+        config.fallback_policy.as_str().into(),
+    );
+    table.insert(
+        "max_parallel_renewals".to_owned(),
+        integer(config.max_parallel_renewals),
+    );
+    if !config.expiry_warnings.is_empty() {
+        let thresholds = config
+            .expiry_warnings
+            .iter()
+            .map(|threshold| duration::format(*threshold).into())
+            .collect();
+        table.insert("expiry_warnings".to_owned(), Value::Array(thresholds));
+    }
+    let dedup_windows = config
+        .dedup_windows
+        .iter()
+        .map(|(event, window)| format!("{event}={}", duration::format(*window)).into())
+        .collect();
+    table.insert("dedup_windows".to_owned(), Value::Array(dedup_windows));
+    if let Some(limit) = config.notify_rate_limit {
+        table.insert("notify_rate_limit".to_owned(), limit.to_string().into());
+    }
+    if let Some(attempts) = config.escalate_after {
+        table.insert("escalate_after".to_owned(), i64::from(attempts).into());
+    }
+    if !config.notifiers.is_empty() {
+        let notifiers = config
+            .notifiers
+            .iter()
+            .map(|spec| {
+                let mut entry = Table::new();
+                entry.insert("name".to_owned(), spec.name.clone().into());
+                entry.insert("type".to_owned(), spec.kind().as_str().into());
+                // Webhook URLs carry their credentials, SMTP URLs cannot
+                let url = match spec.settings {
+                    NotifierSettings::Email(_) => spec.url.as_str(),
+                    _ => REDACTED,
+                };
+                entry.insert("url".to_owned(), url.into());
+                entry.insert("min_severity".to_owned(), spec.min_severity.as_str().into());
+                if spec.escalation {
+                    entry.insert("escalation".to_owned(), true.into());
+                }
+                match &spec.settings {
+                    NotifierSettings::Slack => {}
+                    NotifierSettings::Webhook(webhook) => insert_webhook(&mut entry, webhook),
+                    NotifierSettings::Email(email) => insert_email(&mut entry, email),
+                }
+                Value::Table(entry)
+            })
+            .collect();
+        table.insert("notifiers".to_owned(), Value::Array(notifiers));
+    }
+
+    if let Some(ca) = &config.step_ca {
+        let mut step_ca = Table::new();
+        step_ca.insert("url".to_owned(), ca.url.clone().into());
+        for (key, value) in [
+            ("fingerprint", &ca.root_fingerprint),
+            ("root", &ca.root_cert),
+            ("provisioner", &ca.provisioner),
+            ("provisioner_password_file", &ca.password_file),
+            ("provisioner_key_file", &ca.provisioner_key_file),
+            ("token_file", &ca.token_file),
+        ] {
+            if let Some(value) = value {
+                step_ca.insert(key.to_owned(), value.clone().into());
+            }
+        }
+        table.insert("step_ca".to_owned(), Value::Table(step_ca));
+    }
+
+    if !config.certificates.is_empty() {
+        let certificates = config
+            .certificates
+            .iter()
+            .map(|spec| {
+                let mut entry = Table::new();
+                entry.insert("name".to_owned(), spec.name.clone().into());
+                insert_certificate(&mut entry, spec);
+                Value::Table(entry)
+            })
+            .collect();
+        table.insert("certificates".to_owned(), Value::Array(certificates));
+    }
+
+    table.to_string()
+}
+
+/// The settings of webhook notifier `webhook`, with header values redacted.
+fn insert_webhook(entry: &mut Table, webhook: &WebhookSpec) {
+    if let Some(template) = &webhook.template {
+        entry.insert("template".to_owned(), template.to_string().into());
+    }
+    if !webhook.headers.is_empty() {
+        let headers = webhook
+            .headers
+            .iter()
+            .map(|(name, _)| format!("{name}: {REDACTED}").into())
+            .collect();
+        entry.insert("headers".to_owned(), Value::Array(headers));
+    }
+    if let Some(secret_file) = &webhook.secret_file {
+        entry.insert("secret_file".to_owned(), secret_file.clone().into());
+        entry.insert(
+            "signature_header".to_owned(),
+            webhook.signature_header.clone().into(),
+        );
+    }
+}
+
+/// The settings of email notifier `email`.
+fn insert_email(entry: &mut Table, email: &EmailSpec) {
+    entry.insert("tls".to_owned(), email.tls.as_str().into());
+    entry.insert("from".to_owned(), email.from.to_string().into());
+    entry.insert(
+        "to".to_owned(),
+        Value::Array(email.to.iter().map(|to| to.to_string().into()).collect()),
+    );
+    if let (Some(username), Some(password_file)) = (&email.username, &email.password_file) {
+        entry.insert("username".to_owned(), username.clone().into());
+        entry.insert("password_file".to_owned(), password_file.clone().into());
+    }
+}
+
+/// The per-certificate settings of `spec`, without its name.
+fn insert_certificate(table: &mut Table, spec: &CertSpec) {
+    if !spec.server_ip.is_empty() {
+        table.insert("server_ip".to_owned(), spec.server_ip.clone().into());
+        table.insert("cert_domains".to_owned(), sans(&spec.subject_alt_names));
+    }
+    table.insert("cert_dir".to_owned(), spec.cert_dir.clone().into());
+    table.insert(
+        "cert_validity_days".to_owned(),
+        duration::format(spec.cert_validity).into(),
+    );
+    table.insert(
+        "days_before_renewal".to_owned(),
+        duration::format(spec.renew_before).into(),
+    );
+    if let Some(percent) = spec.renew_at_percent {
+        table.insert("renew_at_percent".to_owned(), integer(percent));
+    }
+    if let Some(command) = &spec.reload_command {
+        table.insert("reload_command".to_owned(), command.clone().into());
+    }
+    table.insert("cert_profile".to_owned(), spec.profile.kind.as_str().into());
+    table.insert(
+        "cert_subject".to_owned(),
+        spec.profile.subject.to_string().into(),
+    );
+    if !spec.profile.policy_oids.is_empty() {
+        table.insert(
+            "cert_policy_oids".to_owned(),
+            Value::Array(
+                spec.profile
+                    .policy_oids
+                    .iter()
+                    .map(|oid| oid.to_string().into())
+                    .collect(),
+            ),
+        );
+    }
+}
+
+fn sans(names: &[SubjectAltName]) -> Value {
+    if names.is_empty() {
+        return "none".into();
+    }
+    Value::Array(names.iter().map(|san| san.to_string().into()).collect())
+}
+
+fn integer(value: impl TryInto<i64>) -> Value {
+    Value::Integer(value.try_into().unwrap_or(i64::MAX))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NotifierSpec, Severity};
+
+    #[test]
+    fn test_file_is_flattened_to_setting_names() {
+        let settings = Settings::from_toml(
+            r#"
+            server_ip = "10.0.0.5"
+            cert_domains = ["api.internal", "10.0.0.6"]
+            check_interval = 600
+
+            [step_ca]
+            url = "https://ca.internal:9000"
+            provisioner-key-file = "/run/secrets/jwk"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.get("SERVER_IP"), Some("10.0.0.5"));
+        assert_eq!(settings.get("CERT_DOMAINS"), Some("api.internal,10.0.0.6"));
+        assert_eq!(settings.get("CHECK_INTERVAL"), Some("600"));
+        assert_eq!(
+            settings.get("STEP_CA_URL"),
+            Some("https://ca.internal:9000")
+        );
+        assert_eq!(
+            settings.get("STEP_CA_PROVISIONER_KEY_FILE"),
+            Some("/run/secrets/jwk")
+        );
+    }
+
+    #[test]
+    fn test_certificate_tables_become_prefixed_settings() {
+        let settings = Settings::from_toml(
+            r#"
+            [[certificates]]
+            name = "api-gateway"
+            cert_domains = ["api.internal"]
+
+            [[certificates]]
+            name = "db"
+            server_ip = "10.0.0.7"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.get("CERTIFICATES"), Some("api-gateway,db"));
+        assert_eq!(
+            settings.get("API_GATEWAY_CERT_DOMAINS"),
+            Some("api.internal")
+        );
+        assert_eq!(settings.get("DB_SERVER_IP"), Some("10.0.0.7"));
+
+        let settings = Settings::from_toml(
+            r#"
+            [[notifiers]]
+            name = "on-call"
+            type = "webhook"
+            url = "https://pager.example.com/hook"
+            headers = ["Accept: application/json, */*", "X-Team: platform"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(settings.get("NOTIFIERS"), Some("on-call"));
+        assert_eq!(settings.get("NOTIFIER_ON_CALL_TYPE"), Some("webhook"));
+        assert_eq!(
+            settings.get("NOTIFIER_ON_CALL_HEADERS"),
+            Some("Accept: application/json, */*\nX-Team: platform")
+        );
+
+        assert!(Settings::from_toml("[[certificates]]\nserver_ip = \"10.0.0.7\"").is_err());
+        assert!(Settings::from_toml("cert_domains = [[\"a\"]]").is_err());
+        assert!(Settings::from_toml("server_ip = ").is_err());
+    }
+
+    #[test]
+    fn test_effective_config_round_trips() {
+        let mut config = Config::test_default();
+        let webhook = WebhookSpec {
+            template: Some(serde_json::json!({ "summary": "{{service}}: {{message}}" })),
+            headers: vec![
+                ("Authorization".to_owned(), "Bearer secret".to_owned()),
+                ("Accept".to_owned(), "application/json, */*".to_owned()),
+            ],
+            secret_file: Some("/etc/dimension-bridge/webhook.key".to_owned()),
+            signature_header: "X-Hub-Signature-256".to_owned(),
+        };
+        config.notifiers = vec![NotifierSpec {
+            name: "oncall".to_owned(),
+            url: "https://pager.example.com/secret".to_owned(),
+            min_severity: Severity::Error,
+            escalation: true,
+            settings: NotifierSettings::Webhook(webhook.clone()),
+        }];
+        let mut settings = Settings::default();
+        settings.set("NOTIFIERS", "mail");
+        settings.set("NOTIFIER_MAIL_TYPE", "email");
+        settings.set("NOTIFIER_MAIL_URL", "smtp://mail.example.com:2525");
+        settings.set("NOTIFIER_MAIL_TLS", "tls");
+        settings.set("NOTIFIER_MAIL_FROM", "Certificates <certs@example.com>");
+        settings.set(
+            "NOTIFIER_MAIL_TO",
+            "ops@example.com,\"Site Reliability\" <sre@example.com>",
+        );
+        settings.set("NOTIFIER_MAIL_USERNAME", "certs");
+        settings.set(
+            "NOTIFIER_MAIL_PASSWORD_FILE",
+            "/etc/dimension-bridge/smtp.pass",
+        );
+        let mut validation = Validation::default();
+        config
+            .notifiers
+            .extend(NotifierSpec::load_all(&settings, &mut validation));
+        assert!(validation.finish().is_ok());
+        // The default chain is spelled out
+        config.issuers = Some(config.issuer_chain());
+        config.expiry_warnings = vec![Duration::days(14), Duration::hours(36)];
+        config.dedup_windows =
+            crate::notifier::throttle::parse_dedup_windows("failed=90m,renewed=0").unwrap();
+        config.notify_rate_limit = Some("20/1h".parse().unwrap());
+        config.escalate_after = Some(3);
+
+        let text = to_toml(&config);
+        assert!(text.contains("issuers = [\"step-cli\", \"self-signed\"]"));
+        assert!(text.contains("expiry_warnings = [\"14d\", \"1d12h\"]"));
+        assert!(text.contains(
+            "dedup_windows = [\"clock-skew=6h\", \"backup-failed=6h\", \"failed=1h30m\", \"renewed=0s\"]"
+        ));
+        assert!(text.contains("notify_rate_limit = \"20/1h\""));
+        assert!(text.contains("escalation = true"));
+        assert!(text.contains("url = \"<redacted>\""));
+        assert!(text.contains("url = \"smtp://mail.example.com:2525\""));
+        assert!(!text.contains("pager.example.com/secret"));
+        assert!(!text.contains("Bearer secret"));
+        assert!(text.contains("headers = [\"Authorization: <redacted>\", \"Accept: <redacted>\"]"));
+
+        let settings = Settings::from_toml(&text).unwrap();
+        let mut reloaded = Config::from_settings(&settings).unwrap();
+        assert_eq!(reloaded.notifiers[0].url, REDACTED);
+        reloaded.notifiers[0]
+            .url
+            .clone_from(&config.notifiers[0].url);
+        // Only the header values are redacted
+        if let NotifierSettings::Webhook(reloaded_webhook) = &mut reloaded.notifiers[0].settings {
+            assert_eq!(
+                reloaded_webhook.headers,
+                [
+                    ("Authorization".to_owned(), REDACTED.to_owned()),
+                    ("Accept".to_owned(), REDACTED.to_owned()),
+                ]
+            );
+            reloaded_webhook.headers = webhook.headers;
+        }
+        assert_eq!(reloaded, config);
+    }
+}
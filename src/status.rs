@@ -0,0 +1,209 @@
+//! Deployed certificate status.
+
+use crate::duration;
+use chrono::{DateTime, Duration, Utc};
+use std::fmt;
+
+/// State of the deployed certificate and key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CertStatus {
+    /// No certificate has been deployed yet.
+    Missing,
+    /// The certificate or key exists but cannot be read or parsed.
+    Unreadable {
+        /// Why the files could not be used.
+        reason: String,
+    },
+    /// The private key does not belong to the certificate.
+    KeyMismatch,
+    /// The certificate is not valid yet, usually a sign of clock skew.
+    NotYetValid {
+        /// Start of the validity period.
+        not_before: DateTime<Utc>,
+    },
+    /// The certificate has expired.
+    Expired {
+        /// End of the validity period.
+        not_after: DateTime<Utc>,
+    },
+    /// The certificate no longer matches the configuration, e.g. its key type.
+    Outdated {
+        /// What differs from the configuration.
+        reason: String,
+    },
+    /// The certificate is valid but inside the renewal window.
+    RenewalDue {
+        /// Time left until expiry.
+        remaining: Duration,
+    },
+    /// A fallback certificate is deployed because the trusted issuers failed.
+    /// It is valid, but clients do not trust it, so a trusted issuer is
+    /// asked again at every check.
+    Fallback {
+        /// Issuer of the deployed certificate, e.g. `self-signed`.
+        issuer: String,
+        /// Time left until expiry.
+        remaining: Duration,
+    },
+    /// The certificate is valid and outside the renewal window.
+    Healthy {
+        /// Time left until expiry.
+        remaining: Duration,
+    },
+}
+
+impl CertStatus {
+    /// Whether a new certificate should be issued.
+    ///
+    /// A certificate that is not valid yet is left alone: issuing again under
+    /// the same clock would not help.
+    #[must_use]
+    pub const fn needs_renewal(&self) -> bool {
+        !matches!(self, Self::Healthy { .. } | Self::NotYetValid { .. })
+    }
+
+    /// Process exit code reported by the `health` and `once` commands.
+    ///
+    /// `1` is left for runtime errors.
+    #[must_use]
+    pub const fn exit_code(&self) -> i32 {
+        match self {
+            Self::Healthy { .. } => 0,
+            Self::RenewalDue { .. } => 2,
+            Self::Expired { .. } => 3,
+            Self::NotYetValid { .. } => 4,
+            Self::Missing => 5,
+            Self::Unreadable { .. } => 6,
+            Self::KeyMismatch => 7,
+            Self::Outdated { .. } => 8,
+            Self::Fallback { .. } => 9,
+        }
+    }
+}
+
+impl fmt::Display for CertStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing => f.write_str("certificate missing"),
+            Self::Unreadable { reason } => write!(f, "certificate unreadable: {reason}"),
+            Self::KeyMismatch => f.write_str("private key does not match certificate"),
+            Self::NotYetValid { not_before } => {
+                write!(f, "certificate not valid until {not_before}")
+            }
+            Self::Expired { not_after } => write!(f, "certificate expired at {not_after}"),
+            Self::Outdated { reason } => write!(f, "certificate outdated: {reason}"),
+            Self::RenewalDue { remaining } => {
+                write!(
+                    f,
+                    "renewal due ({} remaining)",
+                    duration::format_approx(*remaining)
+                )
+            }
+            Self::Fallback { issuer, remaining } => {
+                write!(
+                    f,
+                    "fallback certificate from {issuer} deployed ({} remaining)",
+                    duration::format_approx(*remaining)
+                )
+            }
+            Self::Healthy { remaining } => {
+                write!(
+                    f,
+                    "healthy ({} remaining)",
+                    duration::format_approx(*remaining)
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_renewal() {
+        assert!(CertStatus::Missing.needs_renewal());
+        assert!(
+            CertStatus::Unreadable {
+                reason: "bad".to_owned()
+            }
+            .needs_renewal()
+        );
+        assert!(CertStatus::KeyMismatch.needs_renewal());
+        assert!(
+            CertStatus::Expired {
+                not_after: Utc::now()
+            }
+            .needs_renewal()
+        );
+        assert!(
+            CertStatus::Outdated {
+                reason: "key type".to_owned()
+            }
+            .needs_renewal()
+        );
+        assert!(
+            CertStatus::RenewalDue {
+                remaining: Duration::days(1)
+            }
+            .needs_renewal()
+        );
+        assert!(
+            CertStatus::Fallback {
+                issuer: "self-signed".to_owned(),
+                remaining: Duration::days(10)
+            }
+            .needs_renewal()
+        );
+        assert!(
+            !CertStatus::Healthy {
+                remaining: Duration::days(10)
+            }
+            .needs_renewal()
+        );
+        assert!(
+            !CertStatus::NotYetValid {
+                not_before: Utc::now()
+            }
+            .needs_renewal()
+        );
+    }
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let now = Utc::now();
+        let codes = [
+            CertStatus::Healthy {
+                remaining: Duration::days(10),
+            }
+            .exit_code(),
+            CertStatus::RenewalDue {
+                remaining: Duration::days(1),
+            }
+            .exit_code(),
+            CertStatus::Expired { not_after: now }.exit_code(),
+            CertStatus::NotYetValid { not_before: now }.exit_code(),
+            CertStatus::Missing.exit_code(),
+            CertStatus::Unreadable {
+                reason: String::new(),
+            }
+            .exit_code(),
+            CertStatus::KeyMismatch.exit_code(),
+            CertStatus::Outdated {
+                reason: String::new(),
+            }
+            .exit_code(),
+            CertStatus::Fallback {
+                issuer: String::new(),
+                remaining: Duration::days(10),
+            }
+            .exit_code(),
+        ];
+
+        let unique: std::collections::HashSet<_> = codes.iter().collect();
+        assert_eq!(unique.len(), codes.len());
+        assert_eq!(codes.first(), Some(&0));
+        assert!(!codes.contains(&1), "exit code 1 is reserved for errors");
+    }
+}
@@ -0,0 +1,489 @@
+//! Online enrollment against a step-ca server.
+//!
+//! Certificates are requested with `step ca certificate`, authenticated either
+//! with a provisioner password or a pre-minted one-time token, so they chain
+//! to the internal CA instead of being self-signed. Once issued, they are
+//! renewed with `step ca renew`, which authenticates with the certificate
+//! itself over mTLS.
+//!
+//! With a JWK provisioner key configured, the built-in [`StepCaClient`] is used
+//! instead: it mints the one-time token itself and calls the CA API directly,
+//! so the `step` binary is not needed.
+
+pub mod client;
+pub mod token;
+
+pub use client::{SignedCertificate, StepCaClient, StepCaError};
+pub use token::{ProvisionerKey, TokenRequest};
+
+use crate::{
+    Error, duration,
+    key::KeyType,
+    profile::CertProfile,
+    san::SubjectAltName,
+    self_signed::GeneratedCert,
+    settings::{ConfigError, Settings},
+};
+use chrono::Utc;
+use rcgen::CertificateParams;
+use tokio::fs;
+
+/// Connection and credentials for a step-ca server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepCaConfig {
+    /// CA URL, e.g. `https://ca.company.internal:9000`.
+    pub url: String,
+    /// Provisioner name, required when the CA has more than one.
+    pub provisioner: Option<String>,
+    /// File holding the provisioner password.
+    pub password_file: Option<String>,
+    /// File holding the JWK provisioner key, plain or password-encrypted.
+    /// Selects the built-in client.
+    pub provisioner_key_file: Option<String>,
+    /// File holding a one-time enrollment token.
+    pub token_file: Option<String>,
+    /// SHA-256 fingerprint of the root certificate, as lowercase hex.
+    pub root_fingerprint: Option<String>,
+    /// Path of the root certificate. Downloaded on first use when only the
+    /// fingerprint is known.
+    pub root_cert: Option<String>,
+}
+
+/// Certificate to request from the CA.
+#[derive(Debug, Clone)]
+pub struct Enrollment<'a> {
+    /// Subject common name.
+    pub subject: &'a str,
+    /// Subject alternative names.
+    pub subject_alt_names: &'a [SubjectAltName],
+    /// Where `step` writes the certificate chain.
+    pub cert_path: &'a str,
+    /// Where `step` writes the private key.
+    pub key_path: &'a str,
+    /// Requested validity.
+    pub validity: chrono::Duration,
+    /// Algorithm of the generated key.
+    pub key_type: KeyType,
+    /// Subject fields, usages and policies to request.
+    pub profile: &'a CertProfile,
+    /// Where `step` reads the profile as template data.
+    pub template_data_path: &'a str,
+}
+
+impl StepCaConfig {
+    /// Load the step-ca settings from the `STEP_CA_*` settings.
+    ///
+    /// Returns `None` when `STEP_CA_URL` is not set.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`ConfigError`] if the CA root cannot be established (neither
+    /// `STEP_CA_FINGERPRINT` nor `STEP_CA_ROOT` is set, or the fingerprint is
+    /// malformed), or if the provisioner credentials are incomplete or
+    /// ambiguous.
+    pub fn from_settings(settings: &Settings) -> Result<Option<Self>, ConfigError> {
+        let Some(url) = non_empty_var(settings, "STEP_CA_URL") else {
+            return Ok(None);
+        };
+
+        let root_fingerprint = non_empty_var(settings, "STEP_CA_FINGERPRINT")
+            .map(|fingerprint| normalize_fingerprint(&fingerprint))
+            .transpose()
+            .map_err(|e| e.to_string())?;
+        let root_cert = non_empty_var(settings, "STEP_CA_ROOT");
+        if root_fingerprint.is_none() && root_cert.is_none() {
+            return Err("STEP_CA_URL requires STEP_CA_FINGERPRINT or STEP_CA_ROOT".into());
+        }
+
+        let provisioner = non_empty_var(settings, "STEP_CA_PROVISIONER");
+        let password_file = non_empty_var(settings, "STEP_CA_PROVISIONER_PASSWORD_FILE");
+        let provisioner_key_file = non_empty_var(settings, "STEP_CA_PROVISIONER_KEY_FILE");
+        let token_file = non_empty_var(settings, "STEP_CA_TOKEN_FILE");
+
+        if provisioner_key_file.is_some() {
+            // The password file, if any, decrypts the key
+            if token_file.is_some() {
+                return Err(
+                    "STEP_CA_PROVISIONER_KEY_FILE and STEP_CA_TOKEN_FILE are mutually exclusive"
+                        .into(),
+                );
+            }
+            if provisioner.is_none() {
+                return Err("STEP_CA_PROVISIONER_KEY_FILE requires STEP_CA_PROVISIONER".into());
+            }
+        } else if password_file.is_some() == token_file.is_some() {
+            return Err(
+                "STEP_CA_URL requires STEP_CA_PROVISIONER_KEY_FILE, or exactly one of \
+                 STEP_CA_PROVISIONER_PASSWORD_FILE or STEP_CA_TOKEN_FILE"
+                    .into(),
+            );
+        }
+
+        Ok(Some(Self {
+            url,
+            provisioner,
+            password_file,
+            provisioner_key_file,
+            token_file,
+            root_fingerprint,
+            root_cert,
+        }))
+    }
+
+    /// Whether enrollment and renewal go through the built-in client rather
+    /// than the `step` binary. Enrollment with a one-time token always does.
+    #[must_use]
+    pub const fn uses_builtin_client(&self) -> bool {
+        self.provisioner_key_file.is_some()
+    }
+
+    /// Path of the root certificate, `{cert_dir}/root_ca.crt` unless configured.
+    #[must_use]
+    pub fn root_path(&self, cert_dir: &str) -> String {
+        self.root_cert
+            .clone()
+            .unwrap_or_else(|| format!("{cert_dir}/root_ca.crt"))
+    }
+
+    /// Arguments for `step ca root`, which downloads the root certificate and
+    /// verifies it against the configured fingerprint.
+    ///
+    /// Returns `None` when no fingerprint is configured.
+    #[must_use]
+    pub fn root_args(&self, root_path: &str) -> Option<Vec<String>> {
+        let fingerprint = self.root_fingerprint.as_deref()?;
+        Some(
+            [
+                "ca",
+                "root",
+                root_path,
+                "--ca-url",
+                &self.url,
+                "--fingerprint",
+                fingerprint,
+                "--force",
+            ]
+            .map(str::to_owned)
+            .to_vec(),
+        )
+    }
+
+    /// Arguments for `step ca renew`, which authenticates with the current
+    /// certificate and key over mTLS and writes the renewed certificate to `out_path`.
+    #[must_use]
+    pub fn renew_args(
+        &self,
+        cert_path: &str,
+        key_path: &str,
+        root_path: &str,
+        out_path: &str,
+    ) -> Vec<String> {
+        [
+            "ca", "renew", cert_path, key_path, "--ca-url", &self.url, "--root", root_path,
+            "--out", out_path, "--force",
+        ]
+        .map(str::to_owned)
+        .to_vec()
+    }
+
+    /// Arguments for `step ca certificate` with the provisioner password.
+    ///
+    /// A one-time token is never put on the command line, where other local
+    /// users could read it; see [`Self::enroll`].
+    #[must_use]
+    pub fn certificate_args(&self, enrollment: &Enrollment<'_>, root_path: &str) -> Vec<String> {
+        let mut args: Vec<String> = [
+            "ca",
+            "certificate",
+            enrollment.subject,
+            enrollment.cert_path,
+            enrollment.key_path,
+            "--ca-url",
+            &self.url,
+            "--root",
+            root_path,
+            "--not-after",
+            &duration::format_go(enrollment.validity),
+            "--set-file",
+            enrollment.template_data_path,
+            "--force",
+        ]
+        .map(str::to_owned)
+        .to_vec();
+
+        if let Some(provisioner) = &self.provisioner {
+            args.extend(["--provisioner".to_owned(), provisioner.clone()]);
+        }
+        if let Some(password_file) = &self.password_file {
+            args.extend([
+                "--provisioner-password-file".to_owned(),
+                password_file.clone(),
+            ]);
+        }
+
+        for san in enrollment.subject_alt_names {
+            args.extend(["--san".to_owned(), san.value()]);
+        }
+        args.extend(enrollment.key_type.step_args().map(str::to_owned));
+
+        args
+    }
+
+    /// Load the JWK provisioner key, decrypting it with the password file if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if no key file is configured, a file cannot be read, or
+    /// the key cannot be decrypted or used.
+    pub async fn provisioner_key(&self) -> Result<ProvisionerKey, StepCaError> {
+        let key_file = self.provisioner_key_file.as_deref().ok_or_else(|| {
+            StepCaError::ProvisionerKey("no provisioner key file configured".to_owned())
+        })?;
+        let key = read_secret(key_file).await?;
+        let password = match &self.password_file {
+            Some(password_file) => Some(read_secret(password_file).await?),
+            None => None,
+        };
+
+        ProvisionerKey::from_file_contents(&key, password.as_deref())
+    }
+
+    /// Enroll through the built-in client: generate a key and CSR, then have
+    /// the CA sign the request with the one-time token of `token_file`, or a
+    /// token minted with the provisioner key.
+    ///
+    /// The `cert_path` and `key_path` of `enrollment` are ignored; the caller
+    /// decides where the result goes.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the token or provisioner key cannot be loaded, the
+    /// CSR cannot be built, or the CA rejects the request.
+    pub async fn enroll(
+        &self,
+        client: &StepCaClient,
+        enrollment: &Enrollment<'_>,
+    ) -> Result<GeneratedCert, StepCaError> {
+        let token = match &self.token_file {
+            Some(token_file) => read_secret(token_file).await?.trim().to_owned(),
+            None => self.mint_token(enrollment).await?,
+        };
+
+        // RSA key generation can take a while, keep it off the async workers
+        let key_type = enrollment.key_type;
+        let key = tokio::task::spawn_blocking(move || key_type.generate())
+            .await
+            .map_err(|e| StepCaError::ProvisionerKey(format!("key generation aborted: {e}")))??;
+        let csr_pem = certificate_request(enrollment, &key)?;
+
+        let signed = client
+            .sign(
+                &csr_pem,
+                &token,
+                &duration::format_go(enrollment.validity),
+                &enrollment.profile.template_data(enrollment.subject),
+            )
+            .await?;
+
+        Ok(GeneratedCert {
+            cert_pem: signed.chain_pem,
+            key_pem: key.serialize_pem(),
+        })
+    }
+
+    /// A token for `enrollment`, signed with the provisioner key.
+    async fn mint_token(&self, enrollment: &Enrollment<'_>) -> Result<String, StepCaError> {
+        let provisioner_key = self.provisioner_key().await?;
+        let sans: Vec<String> = enrollment
+            .subject_alt_names
+            .iter()
+            .map(SubjectAltName::value)
+            .collect();
+        provisioner_key.sign_token(
+            &TokenRequest {
+                provisioner: self.provisioner.as_deref().unwrap_or_default(),
+                ca_url: &self.url,
+                subject: enrollment.subject,
+                sans: &sans,
+                root_fingerprint: self.root_fingerprint.as_deref(),
+            },
+            Utc::now(),
+        )
+    }
+}
+
+/// PEM certificate signing request for an enrollment.
+fn certificate_request(
+    enrollment: &Enrollment<'_>,
+    key: &rcgen::KeyPair,
+) -> Result<String, rcgen::Error> {
+    let mut params = CertificateParams::default();
+    enrollment.profile.apply(&mut params, enrollment.subject);
+    params.subject_alt_names = enrollment
+        .subject_alt_names
+        .iter()
+        .map(SubjectAltName::to_san_type)
+        .collect::<Result<_, _>>()?;
+
+    params.serialize_request(key)?.pem()
+}
+
+async fn read_secret(path: &str) -> Result<String, StepCaError> {
+    fs::read_to_string(path)
+        .await
+        .map_err(|source| StepCaError::File {
+            path: path.to_owned(),
+            source,
+        })
+}
+
+/// Normalize a root fingerprint to lowercase hex without separators.
+///
+/// Accepts the `step ca fingerprint` output as well as colon-separated and
+/// `sha256:`-prefixed forms.
+///
+/// # Errors
+///
+/// Returns [`Error::Parse`] if the value is not a SHA-256 digest.
+pub fn normalize_fingerprint(value: &str) -> crate::Result<String> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix("sha256:")
+        .or_else(|| value.strip_prefix("SHA256:"))
+        .unwrap_or(value);
+    let hex: String = value
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+
+    if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(hex)
+    } else {
+        Err(Error::Parse(format!(
+            "Invalid CA root fingerprint '{value}': expected a SHA-256 hex digest"
+        )))
+    }
+}
+
+fn non_empty_var(settings: &Settings, name: &str) -> Option<String> {
+    settings
+        .get(name)
+        .filter(|value| !value.trim().is_empty())
+        .map(str::to_owned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FINGERPRINT: &str = "4fe2ba4bd2d4ac8dc6bfc5e5d0b3c7ce6bca2fd3b8e1a44dc21a6c5b1e44f1a9";
+
+    fn config() -> StepCaConfig {
+        StepCaConfig {
+            url: "https://ca.example.com:9000".to_owned(),
+            provisioner: Some("admin".to_owned()),
+            password_file: Some("/run/secrets/provisioner".to_owned()),
+            provisioner_key_file: None,
+            token_file: None,
+            root_fingerprint: Some(FINGERPRINT.to_owned()),
+            root_cert: None,
+        }
+    }
+
+    fn enrollment<'a>(sans: &'a [SubjectAltName], profile: &'a CertProfile) -> Enrollment<'a> {
+        Enrollment {
+            subject: "api.example.com",
+            subject_alt_names: sans,
+            cert_path: "/certs/api-new.crt",
+            key_path: "/certs/api-new.key",
+            validity: chrono::Duration::days(15),
+            key_type: KeyType::EcdsaP256,
+            profile,
+            template_data_path: "/certs/api-new.json",
+        }
+    }
+
+    #[test]
+    fn test_normalize_fingerprint() {
+        assert_eq!(normalize_fingerprint(FINGERPRINT).unwrap(), FINGERPRINT);
+        assert_eq!(
+            normalize_fingerprint(&format!("SHA256:{}", FINGERPRINT.to_uppercase())).unwrap(),
+            FINGERPRINT
+        );
+
+        let colons = FINGERPRINT
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| std::str::from_utf8(pair).unwrap())
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(normalize_fingerprint(&colons).unwrap(), FINGERPRINT);
+
+        assert!(normalize_fingerprint("abcd").is_err());
+        assert!(normalize_fingerprint(&FINGERPRINT.replace('a', "z")).is_err());
+    }
+
+    #[test]
+    fn test_root_path_and_args() {
+        let mut config = config();
+        assert_eq!(config.root_path("/certs"), "/certs/root_ca.crt");
+
+        let args = config.root_args("/certs/root_ca.crt").unwrap();
+        assert_eq!(
+            args,
+            [
+                "ca",
+                "root",
+                "/certs/root_ca.crt",
+                "--ca-url",
+                "https://ca.example.com:9000",
+                "--fingerprint",
+                FINGERPRINT,
+                "--force",
+            ]
+        );
+
+        config.root_cert = Some("/etc/step/root.crt".to_owned());
+        config.root_fingerprint = None;
+        assert_eq!(config.root_path("/certs"), "/etc/step/root.crt");
+        assert!(config.root_args("/etc/step/root.crt").is_none());
+    }
+
+    #[test]
+    fn test_renew_args() {
+        let args = config().renew_args(
+            "/certs/api.crt",
+            "/certs/api.key",
+            "/certs/root_ca.crt",
+            "/certs/api-new.crt",
+        );
+        assert_eq!(
+            args.join(" "),
+            "ca renew /certs/api.crt /certs/api.key --ca-url https://ca.example.com:9000 \
+             --root /certs/root_ca.crt --out /certs/api-new.crt --force"
+        );
+    }
+
+    #[test]
+    fn test_certificate_args_with_password() {
+        let sans = [
+            SubjectAltName::Dns("api.example.com".to_owned()),
+            SubjectAltName::Ip("10.0.0.5".parse().unwrap()),
+        ];
+        let profile = CertProfile::for_service("api");
+        let args = config().certificate_args(&enrollment(&sans, &profile), "/certs/root_ca.crt");
+        let args = args.join(" ");
+
+        assert!(args.starts_with(
+            "ca certificate api.example.com /certs/api-new.crt /certs/api-new.key \
+             --ca-url https://ca.example.com:9000 --root /certs/root_ca.crt --not-after 360h \
+             --set-file /certs/api-new.json"
+        ));
+        assert!(args.contains("--provisioner admin"));
+        assert!(args.contains("--provisioner-password-file /run/secrets/provisioner"));
+        assert!(args.contains("--san api.example.com --san 10.0.0.5"));
+        assert!(args.ends_with("--kty EC --curve P-256"));
+        assert!(!args.contains("--token"));
+    }
+}
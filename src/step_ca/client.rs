@@ -0,0 +1,230 @@
+//! HTTP client for the step-ca API.
+//!
+//! Talks to `/root`, `/1.0/sign` and `/1.0/renew` directly, so enrollment and
+//! renewal work without the `step` binary.
+
+use crate::certificate;
+use serde_json::{Value, json};
+use std::time::Duration;
+use thiserror::Error;
+
+/// Timeout for a single request to the CA.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Failure talking to step-ca.
+#[derive(Debug, Error)]
+pub enum StepCaError {
+    /// The CA could not be reached or the connection failed.
+    #[error("Step CA request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The CA rejected the request.
+    #[error("Step CA returned {status}: {message}")]
+    Api {
+        /// HTTP status code.
+        status: u16,
+        /// Error message reported by the CA.
+        message: String,
+    },
+    /// The CA answered with something that is not a certificate response.
+    #[error("Invalid Step CA response: {0}")]
+    InvalidResponse(String),
+    /// The root certificate does not have the configured fingerprint.
+    #[error("Step CA root fingerprint is {actual}, expected {expected}")]
+    RootMismatch {
+        /// Configured fingerprint.
+        expected: String,
+        /// Fingerprint of the certificate received.
+        actual: String,
+    },
+    /// A credential or key file could not be read.
+    #[error("Failed to read {path}: {source}")]
+    File {
+        /// Path of the file.
+        path: String,
+        /// Underlying I/O error.
+        source: std::io::Error,
+    },
+    /// The provisioner key cannot be used.
+    #[error("Invalid provisioner key: {0}")]
+    ProvisionerKey(String),
+    /// The certificate request could not be built.
+    #[error("Failed to create certificate request: {0}")]
+    Csr(#[from] rcgen::Error),
+}
+
+impl StepCaError {
+    /// Whether the request may succeed when sent again unchanged.
+    ///
+    /// An unreachable or overloaded CA comes back by itself; rejected
+    /// credentials, another root or an unreadable key file do not.
+    #[must_use]
+    pub const fn is_retryable(&self) -> bool {
+        match self {
+            Self::Http(_) | Self::InvalidResponse(_) => true,
+            Self::Api { status, .. } => matches!(*status, 408 | 429 | 500..=599),
+            Self::RootMismatch { .. }
+            | Self::File { .. }
+            | Self::ProvisionerKey(_)
+            | Self::Csr(_) => false,
+        }
+    }
+}
+
+/// A certificate returned by the CA.
+#[derive(Debug, Clone)]
+pub struct SignedCertificate {
+    /// Leaf certificate followed by the intermediates, in PEM.
+    pub chain_pem: String,
+}
+
+/// Client for one step-ca server.
+#[derive(Debug, Clone)]
+pub struct StepCaClient {
+    url: String,
+    root_pem: Vec<u8>,
+    http: reqwest::Client,
+}
+
+impl StepCaClient {
+    /// Create a client that trusts only the given root certificate.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the root certificate is not valid PEM.
+    pub fn new(url: &str, root_pem: &[u8]) -> Result<Self, StepCaError> {
+        let http = Self::builder(root_pem)?.build()?;
+        Ok(Self {
+            url: url.trim_end_matches('/').to_owned(),
+            root_pem: root_pem.to_vec(),
+            http,
+        })
+    }
+
+    /// Download the root certificate and check it against `fingerprint`.
+    ///
+    /// The CA is not trusted yet at this point, so its TLS certificate is not
+    /// verified; the fingerprint is what authenticates the root.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the request fails or the root has another fingerprint.
+    pub async fn fetch_root(url: &str, fingerprint: &str) -> Result<String, StepCaError> {
+        let http = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+        let response = http
+            .get(format!("{}/root/{fingerprint}", url.trim_end_matches('/')))
+            .send()
+            .await?;
+        let body = Self::json(response).await?;
+
+        let root = pem_field(&body, "ca")?;
+        let actual = certificate::parse_certificate_info(root.as_bytes())
+            .map_err(|e| StepCaError::InvalidResponse(format!("root certificate: {e}")))?
+            .fingerprint_sha256;
+        if actual != fingerprint {
+            return Err(StepCaError::RootMismatch {
+                expected: fingerprint.to_owned(),
+                actual,
+            });
+        }
+
+        Ok(root.to_owned())
+    }
+
+    /// Request a certificate for a CSR, authorized by a one-time token.
+    ///
+    /// `not_after` is an absolute time or a duration such as `360h`.
+    /// `template_data` is passed to the provisioner template as `.Insecure.User`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the request fails or the CA rejects it.
+    pub async fn sign(
+        &self,
+        csr_pem: &str,
+        ott: &str,
+        not_after: &str,
+        template_data: &Value,
+    ) -> Result<SignedCertificate, StepCaError> {
+        let response = self
+            .http
+            .post(format!("{}/1.0/sign", self.url))
+            .json(&json!({
+                "csr": csr_pem,
+                "ott": ott,
+                "notAfter": not_after,
+                "templateData": template_data,
+            }))
+            .send()
+            .await?;
+
+        Self::signed_certificate(&Self::json(response).await?)
+    }
+
+    /// Renew a certificate, authenticating with it over mTLS.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the request fails or the CA rejects the certificate.
+    pub async fn renew(
+        &self,
+        cert_pem: &[u8],
+        key_pem: &[u8],
+    ) -> Result<SignedCertificate, StepCaError> {
+        let identity = reqwest::Identity::from_pem(&[key_pem, b"\n", cert_pem].concat())?;
+        let http = Self::builder(&self.root_pem)?.identity(identity).build()?;
+
+        let response = http.post(format!("{}/1.0/renew", self.url)).send().await?;
+
+        Self::signed_certificate(&Self::json(response).await?)
+    }
+
+    fn builder(root_pem: &[u8]) -> Result<reqwest::ClientBuilder, StepCaError> {
+        Ok(reqwest::Client::builder()
+            .tls_built_in_root_certs(false)
+            .add_root_certificate(reqwest::Certificate::from_pem(root_pem)?)
+            .timeout(REQUEST_TIMEOUT))
+    }
+
+    /// Decode a JSON body, turning CA error responses into [`StepCaError::Api`].
+    async fn json(response: reqwest::Response) -> Result<Value, StepCaError> {
+        let status = response.status();
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            let message = serde_json::from_str::<Value>(&text)
+                .ok()
+                .and_then(|body| body.get("message")?.as_str().map(str::to_owned))
+                .unwrap_or(text);
+            return Err(StepCaError::Api {
+                status: status.as_u16(),
+                message,
+            });
+        }
+
+        serde_json::from_str(&text).map_err(|e| StepCaError::InvalidResponse(e.to_string()))
+    }
+
+    fn signed_certificate(body: &Value) -> Result<SignedCertificate, StepCaError> {
+        let chain: Vec<&str> = match body.get("certChain").and_then(Value::as_array) {
+            Some(chain) if !chain.is_empty() => chain.iter().filter_map(Value::as_str).collect(),
+            _ => vec![pem_field(body, "crt")?, pem_field(body, "ca")?],
+        };
+
+        let chain_pem = chain
+            .iter()
+            .map(|pem| pem.trim_end())
+            .collect::<Vec<_>>()
+            .join("\n")
+            + "\n";
+        Ok(SignedCertificate { chain_pem })
+    }
+}
+
+fn pem_field<'a>(body: &'a Value, field: &str) -> Result<&'a str, StepCaError> {
+    body.get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| StepCaError::InvalidResponse(format!("missing '{field}'")))
+}
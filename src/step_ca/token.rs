@@ -0,0 +1,545 @@
+//! One-time tokens for step-ca JWK provisioners.
+//!
+//! A JWK provisioner authorizes a certificate request with a short-lived JWT
+//! signed by the provisioner key. The key is read either as a plain JWK or as
+//! the password-encrypted JWE that `step` stores it in.
+
+use super::StepCaError;
+use crate::certificate::to_hex;
+use aws_lc_rs::{
+    aead::{AES_128_GCM, AES_256_GCM, Aad, LessSafeKey, Nonce, UnboundKey},
+    digest,
+    key_wrap::{AES_128, AesKek, KeyWrap},
+    pbkdf2,
+    rand::SystemRandom,
+    signature::{
+        ECDSA_P256_SHA256_FIXED_SIGNING, ECDSA_P384_SHA384_FIXED_SIGNING, EcdsaKeyPair,
+        Ed25519KeyPair,
+    },
+};
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+use chrono::{DateTime, Duration, Utc};
+use serde_json::{Value, json};
+use std::num::NonZeroU32;
+
+/// How long a minted token stays valid.
+const TOKEN_LIFETIME_MINUTES: i64 = 5;
+
+/// Signing key of a JWK provisioner.
+#[derive(Debug)]
+pub struct ProvisionerKey {
+    kid: String,
+    signer: Signer,
+}
+
+#[derive(Debug)]
+enum Signer {
+    Es256(EcdsaKeyPair),
+    Es384(EcdsaKeyPair),
+    EdDsa(Ed25519KeyPair),
+}
+
+/// Claims of a sign token.
+#[derive(Debug, Clone)]
+pub struct TokenRequest<'a> {
+    /// Provisioner name (`iss`).
+    pub provisioner: &'a str,
+    /// CA URL; the audience is its `/1.0/sign` endpoint.
+    pub ca_url: &'a str,
+    /// Certificate subject (`sub`).
+    pub subject: &'a str,
+    /// Names the certificate may carry (`sans`).
+    pub sans: &'a [String],
+    /// Root fingerprint (`sha`), binding the token to one CA.
+    pub root_fingerprint: Option<&'a str>,
+}
+
+impl ProvisionerKey {
+    /// Load a provisioner key from the contents of a key file.
+    ///
+    /// Plain JWKs are used as is. Encrypted keys, in compact or JSON JWE
+    /// serialization, are decrypted with `password`.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the key is encrypted and no password was given, if
+    /// decryption fails, or if the key is not a supported signing key.
+    pub fn from_file_contents(data: &str, password: Option<&str>) -> Result<Self, StepCaError> {
+        let data = data.trim();
+
+        let jwk = match serde_json::from_str::<Value>(data) {
+            Ok(value) if value.get("kty").is_some() => value,
+            Ok(value) => decrypt_jwe(&JweParts::from_json(&value)?, password)?,
+            Err(_) => decrypt_jwe(&JweParts::from_compact(data)?, password)?,
+        };
+
+        Self::from_jwk(&jwk)
+    }
+
+    /// Build a provisioner key from a private JWK.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if the JWK is not an EC P-256/P-384 or Ed25519 private key.
+    pub fn from_jwk(jwk: &Value) -> Result<Self, StepCaError> {
+        let kty = jwk_str(jwk, "kty")?;
+        let crv = jwk_str(jwk, "crv")?;
+        let x = jwk_bytes(jwk, "x")?;
+        let d = jwk_bytes(jwk, "d")?;
+
+        let signer = match (kty, crv) {
+            ("EC", "P-256" | "P-384") => {
+                let y = jwk_bytes(jwk, "y")?;
+                let mut point = Vec::with_capacity(1 + x.len() + y.len());
+                point.push(0x04);
+                point.extend_from_slice(&x);
+                point.extend_from_slice(&y);
+
+                let alg = if crv == "P-256" {
+                    &ECDSA_P256_SHA256_FIXED_SIGNING
+                } else {
+                    &ECDSA_P384_SHA384_FIXED_SIGNING
+                };
+                let key = EcdsaKeyPair::from_private_key_and_public_key(alg, &d, &point)
+                    .map_err(|e| key_error(format!("invalid {crv} key: {e}")))?;
+                if crv == "P-256" {
+                    Signer::Es256(key)
+                } else {
+                    Signer::Es384(key)
+                }
+            }
+            ("OKP", "Ed25519") => Signer::EdDsa(
+                Ed25519KeyPair::from_seed_and_public_key(&d, &x)
+                    .map_err(|e| key_error(format!("invalid Ed25519 key: {e}")))?,
+            ),
+            _ => return Err(key_error(format!("unsupported key {kty}/{crv}"))),
+        };
+
+        let kid = match jwk.get("kid").and_then(Value::as_str) {
+            Some(kid) => kid.to_owned(),
+            None => thumbprint(jwk)?,
+        };
+
+        Ok(Self { kid, signer })
+    }
+
+    /// Key ID sent in the token header.
+    #[must_use]
+    pub fn kid(&self) -> &str {
+        &self.kid
+    }
+
+    /// JWS algorithm name of the key.
+    #[must_use]
+    pub const fn algorithm(&self) -> &'static str {
+        match self.signer {
+            Signer::Es256(_) => "ES256",
+            Signer::Es384(_) => "ES384",
+            Signer::EdDsa(_) => "EdDSA",
+        }
+    }
+
+    /// Mint a one-time token for `/1.0/sign`, valid from `now` for five minutes.
+    ///
+    /// # Errors
+    ///
+    /// Returns error if signing fails.
+    pub fn sign_token(
+        &self,
+        request: &TokenRequest<'_>,
+        now: DateTime<Utc>,
+    ) -> Result<String, StepCaError> {
+        let mut jti = [0u8; 16];
+        aws_lc_rs::rand::fill(&mut jti).map_err(|_| key_error("random generation failed"))?;
+
+        let header = json!({
+            "alg": self.algorithm(),
+            "kid": self.kid,
+            "typ": "JWT",
+        });
+        let mut claims = json!({
+            "iss": request.provisioner,
+            "sub": request.subject,
+            "aud": format!("{}/1.0/sign", request.ca_url.trim_end_matches('/')),
+            "sans": request.sans,
+            "iat": now.timestamp(),
+            "nbf": now.timestamp(),
+            "exp": (now + Duration::minutes(TOKEN_LIFETIME_MINUTES)).timestamp(),
+            "jti": to_hex(&jti),
+        });
+        if let (Some(fingerprint), Some(claims)) =
+            (request.root_fingerprint, claims.as_object_mut())
+        {
+            claims.insert("sha".to_owned(), json!(fingerprint));
+        }
+
+        let signing_input = format!(
+            "{}.{}",
+            BASE64URL.encode(header.to_string()),
+            BASE64URL.encode(claims.to_string())
+        );
+        let signature = self.sign(signing_input.as_bytes())?;
+
+        Ok(format!("{signing_input}.{}", BASE64URL.encode(signature)))
+    }
+
+    fn sign(&self, message: &[u8]) -> Result<Vec<u8>, StepCaError> {
+        match &self.signer {
+            Signer::Es256(key) | Signer::Es384(key) => key
+                .sign(&SystemRandom::new(), message)
+                .map(|signature| signature.as_ref().to_vec())
+                .map_err(|_| key_error("signing failed")),
+            Signer::EdDsa(key) => Ok(key.sign(message).as_ref().to_vec()),
+        }
+    }
+}
+
+/// RFC 7638 thumbprint of a public JWK, the key ID step uses by default.
+fn thumbprint(jwk: &Value) -> Result<String, StepCaError> {
+    let canonical = match jwk_str(jwk, "kty")? {
+        "EC" => format!(
+            r#"{{"crv":"{}","kty":"EC","x":"{}","y":"{}"}}"#,
+            jwk_str(jwk, "crv")?,
+            jwk_str(jwk, "x")?,
+            jwk_str(jwk, "y")?
+        ),
+        "OKP" => format!(
+            r#"{{"crv":"{}","kty":"OKP","x":"{}"}}"#,
+            jwk_str(jwk, "crv")?,
+            jwk_str(jwk, "x")?
+        ),
+        kty => return Err(key_error(format!("unsupported key type {kty}"))),
+    };
+    Ok(BASE64URL.encode(digest::digest(&digest::SHA256, canonical.as_bytes())))
+}
+
+/// The five parts of a JWE, with the protected header still encoded.
+struct JweParts {
+    protected: String,
+    encrypted_key: Vec<u8>,
+    iv: Vec<u8>,
+    ciphertext: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+impl JweParts {
+    fn from_compact(data: &str) -> Result<Self, StepCaError> {
+        let parts: Vec<&str> = data.split('.').collect();
+        let [protected, encrypted_key, iv, ciphertext, tag] = parts.as_slice() else {
+            return Err(key_error("not a JWK or JWE"));
+        };
+        Ok(Self {
+            protected: (*protected).to_owned(),
+            encrypted_key: decode(encrypted_key)?,
+            iv: decode(iv)?,
+            ciphertext: decode(ciphertext)?,
+            tag: decode(tag)?,
+        })
+    }
+
+    fn from_json(value: &Value) -> Result<Self, StepCaError> {
+        Ok(Self {
+            protected: jwk_str(value, "protected")?.to_owned(),
+            encrypted_key: jwk_bytes(value, "encrypted_key")?,
+            iv: jwk_bytes(value, "iv")?,
+            ciphertext: jwk_bytes(value, "ciphertext")?,
+            tag: jwk_bytes(value, "tag")?,
+        })
+    }
+}
+
+/// Decrypt a `PBES2-HS256+A128KW` JWE, as written by `step crypto jwk create`.
+fn decrypt_jwe(jwe: &JweParts, password: Option<&str>) -> Result<Value, StepCaError> {
+    let password =
+        password.ok_or_else(|| key_error("key is encrypted, a password file is required"))?;
+
+    let header: Value = serde_json::from_slice(&decode(&jwe.protected)?)
+        .map_err(|e| key_error(format!("invalid JWE header: {e}")))?;
+    let alg = jwk_str(&header, "alg")?;
+    if alg != "PBES2-HS256+A128KW" {
+        return Err(key_error(format!("unsupported key encryption {alg}")));
+    }
+    let enc = match jwk_str(&header, "enc")? {
+        "A128GCM" => &AES_128_GCM,
+        "A256GCM" => &AES_256_GCM,
+        enc => return Err(key_error(format!("unsupported content encryption {enc}"))),
+    };
+    let iterations = header
+        .get("p2c")
+        .and_then(Value::as_u64)
+        .and_then(|count| u32::try_from(count).ok())
+        .and_then(NonZeroU32::new)
+        .ok_or_else(|| key_error("invalid JWE iteration count"))?;
+
+    // The salt is the algorithm name, a zero byte and the p2s value
+    let mut salt = alg.as_bytes().to_vec();
+    salt.push(0);
+    salt.extend_from_slice(&jwk_bytes(&header, "p2s")?);
+
+    let mut kek = [0u8; 16];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        &salt,
+        password.trim_end_matches(['\r', '\n']).as_bytes(),
+        &mut kek,
+    );
+
+    let wrong_password = || key_error("decryption failed, check the password");
+    let mut cek = vec![0u8; jwe.encrypted_key.len()];
+    let cek = AesKek::new(&AES_128, &kek)
+        .map_err(|_| wrong_password())?
+        .unwrap(&jwe.encrypted_key, &mut cek)
+        .map_err(|_| wrong_password())?;
+
+    let key = UnboundKey::new(enc, cek)
+        .map(LessSafeKey::new)
+        .map_err(|_| wrong_password())?;
+    let nonce = Nonce::try_assume_unique_for_key(&jwe.iv).map_err(|_| wrong_password())?;
+    let mut in_out = [jwe.ciphertext.as_slice(), jwe.tag.as_slice()].concat();
+    let plaintext = key
+        .open_in_place(nonce, Aad::from(jwe.protected.as_bytes()), &mut in_out)
+        .map_err(|_| wrong_password())?;
+
+    serde_json::from_slice(plaintext).map_err(|e| key_error(format!("invalid decrypted JWK: {e}")))
+}
+
+fn jwk_str<'a>(value: &'a Value, field: &str) -> Result<&'a str, StepCaError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or_else(|| key_error(format!("missing '{field}'")))
+}
+
+fn jwk_bytes(value: &Value, field: &str) -> Result<Vec<u8>, StepCaError> {
+    decode(jwk_str(value, field)?)
+}
+
+fn decode(data: &str) -> Result<Vec<u8>, StepCaError> {
+    BASE64URL
+        .decode(data)
+        .map_err(|e| key_error(format!("invalid base64url: {e}")))
+}
+
+fn key_error(reason: impl Into<String>) -> StepCaError {
+    StepCaError::ProvisionerKey(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aws_lc_rs::{
+        aead::{Aad, Nonce},
+        encoding::AsBigEndian,
+        signature::{ECDSA_P256_SHA256_FIXED, ED25519, KeyPair, UnparsedPublicKey},
+    };
+
+    /// A fresh P-256 provisioner key as a private JWK.
+    fn p256_jwk() -> Value {
+        let key = EcdsaKeyPair::generate(&ECDSA_P256_SHA256_FIXED_SIGNING).unwrap();
+        let point = key.public_key().as_ref();
+        let d = key.private_key().as_be_bytes().unwrap();
+        json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": BASE64URL.encode(&point[1..33]),
+            "y": BASE64URL.encode(&point[33..65]),
+            "d": BASE64URL.encode(d.as_ref()),
+        })
+    }
+
+    /// Encrypt a JWK the way `step crypto jwk create` does.
+    fn encrypt_jwk(jwk: &Value, password: &str) -> String {
+        let p2s = [7u8; 16];
+        let protected = BASE64URL.encode(
+            json!({
+                "alg": "PBES2-HS256+A128KW",
+                "enc": "A256GCM",
+                "cty": "jwk+json",
+                "p2c": 1000,
+                "p2s": BASE64URL.encode(p2s),
+            })
+            .to_string(),
+        );
+
+        let mut salt = b"PBES2-HS256+A128KW\0".to_vec();
+        salt.extend_from_slice(&p2s);
+        let mut kek = [0u8; 16];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            NonZeroU32::new(1000).unwrap(),
+            &salt,
+            password.as_bytes(),
+            &mut kek,
+        );
+
+        let cek = [9u8; 32];
+        let mut encrypted_key = [0u8; 40];
+        let encrypted_key = AesKek::new(&AES_128, &kek)
+            .unwrap()
+            .wrap(&cek, &mut encrypted_key)
+            .unwrap()
+            .to_vec();
+
+        let iv = [3u8; 12];
+        let key = LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &cek).unwrap());
+        let mut ciphertext = jwk.to_string().into_bytes();
+        let tag = key
+            .seal_in_place_separate_tag(
+                Nonce::assume_unique_for_key(iv),
+                Aad::from(protected.as_bytes()),
+                &mut ciphertext,
+            )
+            .unwrap();
+
+        [
+            protected,
+            BASE64URL.encode(encrypted_key),
+            BASE64URL.encode(iv),
+            BASE64URL.encode(ciphertext),
+            BASE64URL.encode(tag.as_ref()),
+        ]
+        .join(".")
+    }
+
+    /// Decode the header and claims of a token and verify its ES256 signature.
+    fn verify_es256(token: &str, jwk: &Value) -> (Value, Value) {
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+        let (header, claims) = signing_input.split_once('.').unwrap();
+
+        let mut point = vec![0x04];
+        point.extend(decode(jwk_str(jwk, "x").unwrap()).unwrap());
+        point.extend(decode(jwk_str(jwk, "y").unwrap()).unwrap());
+        UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, point)
+            .verify(signing_input.as_bytes(), &decode(signature).unwrap())
+            .unwrap();
+
+        (
+            serde_json::from_slice(&decode(header).unwrap()).unwrap(),
+            serde_json::from_slice(&decode(claims).unwrap()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn test_sign_token_claims() {
+        let jwk = p256_jwk();
+        let key = ProvisionerKey::from_jwk(&jwk).unwrap();
+        let sans = vec!["api.example.com".to_owned(), "10.0.0.5".to_owned()];
+        let now = Utc::now();
+
+        let token = key
+            .sign_token(
+                &TokenRequest {
+                    provisioner: "admin",
+                    ca_url: "https://ca.example.com:9000/",
+                    subject: "api.example.com",
+                    sans: &sans,
+                    root_fingerprint: Some("abcd"),
+                },
+                now,
+            )
+            .unwrap();
+        let (header, claims) = verify_es256(&token, &jwk);
+
+        assert_eq!(header["alg"], "ES256");
+        assert_eq!(header["kid"], key.kid());
+        assert_eq!(claims["iss"], "admin");
+        assert_eq!(claims["sub"], "api.example.com");
+        assert_eq!(claims["aud"], "https://ca.example.com:9000/1.0/sign");
+        assert_eq!(claims["sans"], json!(sans));
+        assert_eq!(claims["sha"], "abcd");
+        assert_eq!(claims["nbf"], now.timestamp());
+        assert_eq!(claims["exp"], now.timestamp() + 300);
+    }
+
+    #[test]
+    fn test_kid_defaults_to_thumbprint() {
+        // RFC 7638, section 3.1
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": "f83OJ3D2xF1Bg8vub9tLe1gHMzV76e8Tus9uPHvRVEU",
+            "y": "x_FEzRu9m36HLN_tue659LNpXW6pCyStikYjKIWI5a0",
+        });
+        assert_eq!(
+            thumbprint(&jwk).unwrap(),
+            "oKIywvGUpTVTyxMQ3bwIIeQUudfr_CkLMjCE19ECD-U"
+        );
+
+        let mut jwk = p256_jwk();
+        let expected = thumbprint(&jwk).unwrap();
+        assert_eq!(ProvisionerKey::from_jwk(&jwk).unwrap().kid(), expected);
+
+        jwk["kid"] = json!("explicit");
+        assert_eq!(ProvisionerKey::from_jwk(&jwk).unwrap().kid(), "explicit");
+    }
+
+    #[test]
+    fn test_encrypted_key_requires_matching_password() {
+        let jwk = p256_jwk();
+        let encrypted = encrypt_jwk(&jwk, "correct horse");
+
+        let key = ProvisionerKey::from_file_contents(&encrypted, Some("correct horse\n")).unwrap();
+        assert_eq!(key.algorithm(), "ES256");
+
+        assert!(matches!(
+            ProvisionerKey::from_file_contents(&encrypted, Some("wrong")),
+            Err(StepCaError::ProvisionerKey(_))
+        ));
+        assert!(ProvisionerKey::from_file_contents(&encrypted, None).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_key_in_json_serialization() {
+        let jwk = p256_jwk();
+        let compact = encrypt_jwk(&jwk, "secret");
+        let parts: Vec<&str> = compact.split('.').collect();
+        let json = json!({
+            "protected": parts[0],
+            "encrypted_key": parts[1],
+            "iv": parts[2],
+            "ciphertext": parts[3],
+            "tag": parts[4],
+        });
+
+        assert!(ProvisionerKey::from_file_contents(&json.to_string(), Some("secret")).is_ok());
+    }
+
+    #[test]
+    fn test_ed25519_key() {
+        let key = Ed25519KeyPair::generate().unwrap();
+        let seed = key.seed().unwrap().as_be_bytes().unwrap();
+        let jwk = json!({
+            "kty": "OKP",
+            "crv": "Ed25519",
+            "x": BASE64URL.encode(key.public_key().as_ref()),
+            "d": BASE64URL.encode(seed.as_ref()),
+        });
+
+        let provisioner = ProvisionerKey::from_jwk(&jwk).unwrap();
+        let token = provisioner
+            .sign_token(
+                &TokenRequest {
+                    provisioner: "admin",
+                    ca_url: "https://ca.example.com",
+                    subject: "api",
+                    sans: &[],
+                    root_fingerprint: None,
+                },
+                Utc::now(),
+            )
+            .unwrap();
+
+        let (signing_input, signature) = token.rsplit_once('.').unwrap();
+        UnparsedPublicKey::new(&ED25519, key.public_key().as_ref())
+            .verify(signing_input.as_bytes(), &decode(signature).unwrap())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_rejects_unsupported_keys() {
+        let rsa = json!({"kty": "RSA", "crv": "", "x": "", "d": ""});
+        assert!(ProvisionerKey::from_jwk(&rsa).is_err());
+        assert!(ProvisionerKey::from_file_contents("not a key", None).is_err());
+    }
+}
@@ -2,6 +2,8 @@
 //!
 //! Tests the command-line interface behavior.
 
+#![allow(clippy::unwrap_used, clippy::expect_used)]
+
 use assert_cmd::Command;
 use predicates::prelude::*;
 use std::env;
@@ -87,6 +89,96 @@ fn test_once_command_with_env_vars() {
 }
 
 #[test]
+fn test_invalid_key_type_is_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let log_dir = temp_dir.path().join("logs");
+
+    cmd()
+        .env_clear()
+        .env("SERVER_IP", "127.0.0.1")
+        .env("CERT_DIR", cert_dir.to_str().unwrap())
+        .env("LOG_DIR", log_dir.to_str().unwrap())
+        .env("KEY_TYPE", "rsa-1024")
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("rsa-1024"));
+}
+
+#[test]
+fn test_invalid_san_is_rejected() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let log_dir = temp_dir.path().join("logs");
+
+    cmd()
+        .env_clear()
+        .env("CERT_DOMAINS", "api.example.com,api.*.example.com")
+        .env("CERT_DIR", cert_dir.to_str().unwrap())
+        .env("LOG_DIR", log_dir.to_str().unwrap())
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("api.*.example.com"));
+
+    assert!(!cert_dir.exists());
+}
+
+#[test]
+fn test_once_manages_listed_certificates() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let web_dir = temp_dir.path().join("web");
+    let log_dir = temp_dir.path().join("logs");
+
+    let run = |command: &str| {
+        cmd()
+            .env_clear()
+            .env("CERTIFICATES", "api, web-frontend")
+            .env("API_CERT_DOMAINS", "api.example.com")
+            .env("WEB_FRONTEND_SERVER_IP", "10.0.0.5")
+            .env("WEB_FRONTEND_CERT_DIR", web_dir.to_str().unwrap())
+            .env("CERT_DIR", cert_dir.to_str().unwrap())
+            .env("LOG_DIR", log_dir.to_str().unwrap())
+            .env("RUST_LOG", "error")
+            .arg(command)
+            .assert()
+    };
+
+    run("once")
+        .success()
+        .stdout(predicate::str::contains("api: healthy"))
+        .stdout(predicate::str::contains("web-frontend: healthy"));
+    assert!(cert_dir.join("api.crt").exists());
+    assert!(web_dir.join("web-frontend.crt").exists());
+    assert!(!cert_dir.join("cert-agent.crt").exists());
+
+    run("health").success();
+}
+
+#[test]
+fn test_listed_certificate_requires_names() {
+    let temp_dir = TempDir::new().unwrap();
+
+    cmd()
+        .env_clear()
+        .env("CERTIFICATES", "api")
+        .env("CERT_DIR", temp_dir.path().join("certs").to_str().unwrap())
+        .env("LOG_DIR", temp_dir.path().join("logs").to_str().unwrap())
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "API_SERVER_IP or API_CERT_DOMAINS",
+        ));
+}
+
+#[test]
+#[allow(clippy::single_element_loop)]
 fn test_help_flag() {
     // Test both -h and --help (if implemented)
     for help_arg in &["help"] {
@@ -133,3 +225,307 @@ fn test_environment_variable_parsing() {
         result.success(); // Should succeed in local dev environment
     }
 }
+
+/// Write a CA root and a leaf signed by it, plus a fake `step` binary that
+/// serves them. Returns the `PATH` to use and the root fingerprint.
+fn fake_step_ca(dir: &std::path::Path) -> (String, String) {
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, Issuer, KeyPair};
+    use std::os::unix::fs::PermissionsExt;
+
+    let fixtures = dir.join("fixtures");
+    let bin = dir.join("bin");
+    std::fs::create_dir_all(&fixtures).unwrap();
+    std::fs::create_dir_all(&bin).unwrap();
+
+    let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+    ca_params
+        .distinguished_name
+        .push(rcgen::DnType::CommonName, "Test Root CA");
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca_key = KeyPair::generate().unwrap();
+    let ca_cert = ca_params.self_signed(&ca_key).unwrap();
+
+    let leaf_params =
+        CertificateParams::new(vec!["localhost".to_owned(), "127.0.0.1".to_owned()]).unwrap();
+    let leaf_key = KeyPair::generate().unwrap();
+    let issuer = Issuer::new(ca_params, ca_key);
+    let leaf_cert = leaf_params.signed_by(&leaf_key, &issuer).unwrap();
+
+    // Same key, inside the renewal window
+    let mut expiring_params =
+        CertificateParams::new(vec!["localhost".to_owned(), "127.0.0.1".to_owned()]).unwrap();
+    expiring_params.not_before = time::OffsetDateTime::now_utc() - time::Duration::days(10);
+    expiring_params.not_after = time::OffsetDateTime::now_utc() + time::Duration::days(2);
+    let expiring_cert = expiring_params.signed_by(&leaf_key, &issuer).unwrap();
+
+    std::fs::write(fixtures.join("root.crt"), ca_cert.pem()).unwrap();
+    std::fs::write(fixtures.join("leaf.crt"), leaf_cert.pem()).unwrap();
+    std::fs::write(fixtures.join("leaf.key"), leaf_key.serialize_pem()).unwrap();
+    std::fs::write(fixtures.join("expiring.crt"), expiring_cert.pem()).unwrap();
+
+    let script = format!(
+        "#!/bin/sh\n\
+         echo \"$@\" >> {fixtures}/calls\n\
+         case \"$1 $2\" in\n\
+         \"ca root\") cp {fixtures}/root.crt \"$3\" ;;\n\
+         \"ca certificate\") cp {fixtures}/leaf.crt \"$4\" && cp {fixtures}/leaf.key \"$5\" ;;\n\
+         \"ca renew\") while [ $# -gt 0 ]; do\n\
+           [ \"$1\" = --out ] && cp {fixtures}/leaf.crt \"$2\"; shift\n\
+         done ;;\n\
+         *) exit 1 ;;\n\
+         esac\n",
+        fixtures = fixtures.display()
+    );
+    let step = bin.join("step");
+    std::fs::write(&step, script).unwrap();
+    std::fs::set_permissions(&step, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let fingerprint =
+        dimension_bridge::certificate::parse_certificate_info(ca_cert.pem().as_bytes())
+            .unwrap()
+            .fingerprint_sha256;
+    (format!("{}:/usr/bin:/bin", bin.display()), fingerprint)
+}
+
+#[test]
+fn test_once_enrolls_with_step_ca() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let log_dir = temp_dir.path().join("logs");
+    let password_file = temp_dir.path().join("password");
+    std::fs::write(&password_file, "secret").unwrap();
+    let (path, fingerprint) = fake_step_ca(temp_dir.path());
+
+    cmd()
+        .env_clear()
+        .env("PATH", path)
+        .env("SERVER_IP", "127.0.0.1")
+        .env("SERVICE_NAME", "enrolled")
+        .env("CERT_DIR", cert_dir.to_str().unwrap())
+        .env("LOG_DIR", log_dir.to_str().unwrap())
+        .env("STEP_CA_URL", "https://ca.example.com:9000")
+        .env("STEP_CA_FINGERPRINT", &fingerprint)
+        .env("STEP_CA_PROVISIONER", "admin")
+        .env(
+            "STEP_CA_PROVISIONER_PASSWORD_FILE",
+            password_file.to_str().unwrap(),
+        )
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .success();
+
+    let deployed = std::fs::read(cert_dir.join("enrolled.crt")).unwrap();
+    let info = dimension_bridge::certificate::parse_certificate_info(&deployed).unwrap();
+    assert_eq!(info.issuer, "CN=Test Root CA");
+    assert!(cert_dir.join("root_ca.crt").exists());
+
+    let calls = std::fs::read_to_string(temp_dir.path().join("fixtures").join("calls")).unwrap();
+    assert!(calls.contains(&format!(
+        "ca root {}",
+        cert_dir.join("root_ca.crt").display()
+    )));
+    assert!(calls.contains("--provisioner admin"));
+    assert!(calls.contains("--provisioner-password-file"));
+    assert!(calls.contains(&format!(
+        "--set-file {}",
+        cert_dir.join("enrolled-profile.json").display()
+    )));
+}
+
+#[test]
+fn test_once_renews_ca_issued_certificate_over_mtls() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let log_dir = temp_dir.path().join("logs");
+    let token_file = temp_dir.path().join("token");
+    std::fs::write(&token_file, "eyJ.token\n").unwrap();
+    let (path, fingerprint) = fake_step_ca(temp_dir.path());
+
+    let fixtures = temp_dir.path().join("fixtures");
+    std::fs::create_dir_all(&cert_dir).unwrap();
+    std::fs::copy(fixtures.join("expiring.crt"), cert_dir.join("renewed.crt")).unwrap();
+    std::fs::copy(fixtures.join("leaf.key"), cert_dir.join("renewed.key")).unwrap();
+
+    cmd()
+        .env_clear()
+        .env("PATH", path)
+        .env("SERVER_IP", "127.0.0.1")
+        .env("SERVICE_NAME", "renewed")
+        .env("CERT_DIR", cert_dir.to_str().unwrap())
+        .env("LOG_DIR", log_dir.to_str().unwrap())
+        .env("STEP_CA_URL", "https://ca.example.com:9000")
+        .env("STEP_CA_FINGERPRINT", &fingerprint)
+        .env("STEP_CA_TOKEN_FILE", token_file.to_str().unwrap())
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .success();
+
+    assert_eq!(
+        std::fs::read(cert_dir.join("renewed.crt")).unwrap(),
+        std::fs::read(fixtures.join("leaf.crt")).unwrap()
+    );
+    assert_eq!(
+        std::fs::read(cert_dir.join("renewed.key")).unwrap(),
+        std::fs::read(fixtures.join("leaf.key")).unwrap()
+    );
+
+    let calls = std::fs::read_to_string(fixtures.join("calls")).unwrap();
+    assert!(calls.contains(&format!(
+        "ca renew {} {}",
+        cert_dir.join("renewed.crt").display(),
+        cert_dir.join("renewed.key").display()
+    )));
+    assert!(!calls.contains("ca certificate"));
+}
+
+#[test]
+fn test_step_ca_root_fingerprint_mismatch_is_not_trusted() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let log_dir = temp_dir.path().join("logs");
+    let token_file = temp_dir.path().join("token");
+    std::fs::write(&token_file, "eyJ.token\n").unwrap();
+    let (path, _) = fake_step_ca(temp_dir.path());
+
+    cmd()
+        .env_clear()
+        .env("PATH", path)
+        .env("SERVER_IP", "127.0.0.1")
+        .env("SERVICE_NAME", "untrusted")
+        .env("CERT_DIR", cert_dir.to_str().unwrap())
+        .env("LOG_DIR", log_dir.to_str().unwrap())
+        .env("STEP_CA_URL", "https://ca.example.com:9000")
+        .env("STEP_CA_FINGERPRINT", "00".repeat(32))
+        .env("STEP_CA_TOKEN_FILE", token_file.to_str().unwrap())
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .code(9);
+
+    // Enrollment is refused, so the self-signed fallback is deployed
+    assert!(cert_dir.join("untrusted.fallback").exists());
+    let deployed = std::fs::read(cert_dir.join("untrusted.crt")).unwrap();
+    let info = dimension_bridge::certificate::parse_certificate_info(&deployed).unwrap();
+    assert_eq!(info.issuer, info.subject);
+
+    let calls = std::fs::read_to_string(temp_dir.path().join("fixtures").join("calls")).unwrap();
+    assert!(!calls.contains("ca certificate"));
+}
+
+#[test]
+fn test_step_ca_requires_credentials() {
+    let temp_dir = TempDir::new().unwrap();
+
+    cmd()
+        .env_clear()
+        .env("SERVER_IP", "127.0.0.1")
+        .env("CERT_DIR", temp_dir.path().join("certs").to_str().unwrap())
+        .env("LOG_DIR", temp_dir.path().join("logs").to_str().unwrap())
+        .env("STEP_CA_URL", "https://ca.example.com:9000")
+        .env("STEP_CA_FINGERPRINT", "00".repeat(32))
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("STEP_CA_TOKEN_FILE"));
+}
+
+#[test]
+fn test_config_file_drives_once() {
+    let temp_dir = TempDir::new().unwrap();
+    let cert_dir = temp_dir.path().join("certs");
+    let config_file = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_file,
+        format!(
+            r#"
+            cert_dir = "{}"
+            log_dir = "{}"
+
+            [[certificates]]
+            name = "api"
+            cert_domains = ["api.example.com"]
+            "#,
+            cert_dir.display(),
+            temp_dir.path().join("logs").display()
+        ),
+    )
+    .unwrap();
+
+    cmd()
+        .env_clear()
+        .env("RUST_LOG", "error")
+        .arg("--config")
+        .arg(config_file.to_str().unwrap())
+        .arg("once")
+        .assert()
+        .success();
+    assert!(cert_dir.join("api.crt").exists());
+}
+
+#[test]
+fn test_config_validate_reports_merged_settings() {
+    let temp_dir = TempDir::new().unwrap();
+    let config_file = temp_dir.path().join("config.toml");
+    std::fs::write(
+        &config_file,
+        r#"
+        server_ip = "10.0.0.5"
+        cert_dir = "/srv/certs"
+        check_interval = 600
+        slack_webhook_url = "https://hooks.slack.com/services/T0/B0/secret"
+        "#,
+    )
+    .unwrap();
+
+    // The environment overrides the file and the command line overrides both
+    cmd()
+        .env_clear()
+        .env("DIMENSION_BRIDGE_CONFIG", config_file.to_str().unwrap())
+        .env("CHECK_INTERVAL", "300")
+        .env("CERT_DIR", "/env/certs")
+        .args(["--cert-dir=/cli/certs", "config", "validate"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("server_ip = \"10.0.0.5\""))
+        .stdout(predicate::str::contains("check_interval = \"5m\""))
+        .stdout(predicate::str::contains("cert_dir = \"/cli/certs\""))
+        .stdout(predicate::str::contains("<redacted>"))
+        .stdout(predicate::str::contains("secret").not());
+
+    cmd()
+        .env_clear()
+        .args(["--config", config_file.to_str().unwrap()])
+        .args(["--key-type", "dsa", "config", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid configuration"));
+
+    cmd()
+        .env_clear()
+        .args(["--no-such-setting", "1", "config", "validate"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Unknown option: --no-such-setting",
+        ));
+}
+
+#[test]
+fn test_invalid_settings_fail_startup() {
+    cmd()
+        .env_clear()
+        .env("SERVER_IP", "127.0.0.1")
+        .env("CHECK_INTERVAL", "1 day")
+        .env("DAYS_BEFORE_RENEWAL", "15")
+        .env("RUST_LOG", "error")
+        .arg("once")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("CHECK_INTERVAL=\"1 day\""))
+        .stderr(predicate::str::contains(
+            "DAYS_BEFORE_RENEWAL=15d must be less than CERT_VALIDITY_DAYS=15d",
+        ));
+}
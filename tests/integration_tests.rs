@@ -2,11 +2,21 @@
 //!
 //! These tests verify the complete certificate lifecycle functionality.
 
-use dimension_bridge::{CertManager, Config};
+#![allow(clippy::unwrap_used, clippy::expect_used, clippy::indexing_slicing)]
+
+use chrono::Duration;
+use dimension_bridge::{
+    CertManager, CertProfile, CertSpec, CertStatus, Config, Error, Event, FallbackPolicy,
+    IssueRequest, IssuedCert, Issuer, KeyAlgorithm, KeyType, Notification, Notifier,
+    NotifierSettings, NotifierSpec, ProfileKind, Severity, Sink, StepCaClient, StepCaConfig,
+    StepCaError, SubjectAltName, issuer::SelfSignedIssuer, notifier::WebhookSpec,
+};
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
 use serial_test::serial;
 use std::fs;
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
-use tokio;
 
 /// Helper function to create a test certificate manager with temporary directories
 async fn create_test_manager() -> (CertManager, TempDir) {
@@ -18,7 +28,7 @@ async fn create_test_manager() -> (CertManager, TempDir) {
         "127.0.0.1",
         "integration-test",
         cert_dir.to_str().unwrap(),
-        60, // 1 minute for testing
+        Duration::minutes(1),
     );
     config.log_dir = log_dir.to_str().unwrap().to_owned();
 
@@ -62,6 +72,28 @@ async fn create_dummy_certificate(
     Ok(())
 }
 
+/// Helper function to write a real self-signed certificate expiring in `valid_days`
+async fn create_real_certificate(
+    cert_path: &str,
+    key_path: &str,
+    valid_days: i64,
+) -> std::io::Result<()> {
+    use rcgen::{CertificateParams, KeyPair};
+    use time::{Duration, OffsetDateTime};
+
+    let mut params =
+        CertificateParams::new(vec!["localhost".to_owned(), "127.0.0.1".to_owned()]).unwrap();
+    params.not_before = OffsetDateTime::now_utc() - Duration::days(2);
+    params.not_after = OffsetDateTime::now_utc() + Duration::days(valid_days);
+    let key = KeyPair::generate().unwrap();
+    let cert = params.self_signed(&key).unwrap();
+
+    tokio::fs::write(cert_path, cert.pem()).await?;
+    tokio::fs::write(key_path, key.serialize_pem()).await?;
+
+    Ok(())
+}
+
 #[tokio::test]
 #[serial]
 async fn test_certificate_manager_initialization() {
@@ -74,18 +106,18 @@ async fn test_certificate_manager_initialization() {
 
 #[tokio::test]
 #[serial]
-async fn test_check_cert_expiry_missing_certificate() {
+async fn test_check_cert_status_missing_certificate() {
     let (manager, _temp_dir) = create_test_manager().await;
 
-    // When no certificate exists, should return false (needs renewal)
-    let result = manager.check_cert_expiry().await;
-    assert!(result.is_ok());
-    assert!(!result.unwrap()); // Should need renewal
+    // When no certificate exists, it should be reported as missing (needs issuance)
+    let status = manager.check_cert_status().await;
+    assert_eq!(status, CertStatus::Missing);
+    assert!(status.needs_renewal());
 }
 
 #[tokio::test]
 #[serial]
-async fn test_check_cert_expiry_valid_certificate() {
+async fn test_check_cert_status_malformed_certificate() {
     let (manager, temp_dir) = create_test_manager().await;
 
     let cert_path = temp_dir.path().join("certs/integration-test.crt");
@@ -96,11 +128,244 @@ async fn test_check_cert_expiry_valid_certificate() {
         .await
         .expect("Failed to create dummy certificate");
 
-    // Note: This test might fail because we're creating dummy certificates
-    // In a real integration test, we'd want to use actual OpenSSL to create valid certs
-    // For now, we'll just verify the function doesn't crash
-    let result = manager.check_cert_expiry().await;
-    assert!(result.is_ok());
+    // The dummy certificate is not valid DER, so it must be reported as unreadable
+    let status = manager.check_cert_status().await;
+    assert!(matches!(status, CertStatus::Unreadable { .. }));
+    assert!(status.needs_renewal());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_check_cert_status_healthy_certificate() {
+    let (manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), 30)
+        .await
+        .expect("Failed to create certificate");
+
+    let validity = manager.cert_validity().await.unwrap();
+    assert!(validity.not_after > chrono::Utc::now() + Duration::days(29));
+
+    // 30 days remaining is well outside the 5 day renewal window
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::Healthy { remaining } if remaining > Duration::days(29)
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_certificate_info_describes_deployed_certificate() {
+    let (manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    // No certificate deployed yet
+    let error = manager.certificate_info().await.unwrap_err();
+    assert!(matches!(&error, Error::Io { path, .. } if path == cert_path.to_str().unwrap()));
+
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), 30)
+        .await
+        .expect("Failed to create certificate");
+
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(
+        info.subject_alt_names,
+        vec![
+            SubjectAltName::Dns("localhost".to_owned()),
+            SubjectAltName::Ip("127.0.0.1".parse().unwrap())
+        ]
+    );
+    assert_eq!(info.key_algorithm, KeyAlgorithm::EcdsaP256);
+    assert_eq!(info.fingerprint_sha256.len(), 64);
+    assert_eq!(info.validity, manager.cert_validity().await.unwrap());
+}
+
+#[tokio::test]
+#[serial]
+async fn test_check_cert_status_within_renewal_window() {
+    let (manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), 2)
+        .await
+        .expect("Failed to create certificate");
+
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::RenewalDue { remaining } if remaining <= Duration::days(2)
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_check_cert_status_expired_certificate() {
+    let (manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), -1)
+        .await
+        .expect("Failed to create certificate");
+
+    let status = manager.check_cert_status().await;
+    assert!(matches!(status, CertStatus::Expired { .. }));
+    assert_eq!(status.exit_code(), 3);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_check_cert_status_key_mismatch() {
+    let (manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), 30)
+        .await
+        .expect("Failed to create certificate");
+
+    // Replace the key with one that does not belong to the certificate
+    let other_key = rcgen::KeyPair::generate().unwrap();
+    tokio::fs::write(&key_path, other_key.serialize_pem())
+        .await
+        .unwrap();
+
+    assert_eq!(manager.check_cert_status().await, CertStatus::KeyMismatch);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_renewal_at_lifetime_percentage() {
+    let (mut manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    // Issued 2 days ago for 32 days: 6.25% of the lifetime has elapsed
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), 30)
+        .await
+        .expect("Failed to create certificate");
+
+    manager.config.renew_at_percent = Some(10);
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::Healthy { .. }
+    ));
+
+    // Far from expiry, but past 5% of the lifetime
+    manager.config.renew_at_percent = Some(5);
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::RenewalDue { remaining } if remaining > Duration::days(29)
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_short_lived_certificate_renews_at_hours_remaining() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.cert_validity = Duration::hours(24);
+    manager.config.renew_before = Duration::hours(8);
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(
+        status,
+        CertStatus::Healthy { remaining }
+            if remaining > Duration::hours(23) && remaining <= Duration::hours(24)
+    ));
+
+    let validity = manager.cert_validity().await.unwrap();
+    assert_eq!(
+        validity.not_after - validity.not_before,
+        Duration::hours(24)
+    );
+
+    // Less than a day is left, which is not yet inside a 23 hour window
+    manager.config.renew_before = Duration::hours(23);
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::Healthy { .. }
+    ));
+    manager.config.renew_before = Duration::hours(24);
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::RenewalDue { .. }
+    ));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_run_once_issues_initial_certificate() {
+    let (manager, _temp_dir) = create_test_manager().await;
+
+    assert_eq!(manager.check_cert_status().await, CertStatus::Missing);
+
+    // Without a CA the self-signed fallback issues the certificate in-process
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }));
+
+    let info = manager.certificate_info().await.unwrap();
+    assert!(
+        info.subject_alt_names
+            .contains(&SubjectAltName::Ip("127.0.0.1".parse().unwrap()))
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_key_type_change_triggers_reissue() {
+    let (mut manager, temp_dir) = create_test_manager().await;
+
+    let cert_path = temp_dir.path().join("certs/integration-test.crt");
+    let key_path = temp_dir.path().join("certs/integration-test.key");
+
+    // Deployed certificate uses an ECDSA P-256 key
+    create_real_certificate(cert_path.to_str().unwrap(), key_path.to_str().unwrap(), 30)
+        .await
+        .expect("Failed to create certificate");
+
+    manager.config.key_type = Some(KeyType::Ed25519);
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::Outdated { .. }
+    ));
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }));
+
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(info.key_algorithm, KeyAlgorithm::Ed25519);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_unset_key_type_keeps_deployed_key_type() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.key_type = Some(KeyType::Rsa2048);
+    manager.run_once().await.unwrap();
+
+    manager.config.key_type = None;
+    assert!(matches!(
+        manager.check_cert_status().await,
+        CertStatus::Healthy { .. }
+    ));
+
+    // Renewal keeps the key type of the deployed certificate
+    let serial = manager.certificate_info().await.unwrap().serial;
+    manager.config.renew_before = manager.config.cert_validity;
+    manager.run_once().await.unwrap();
+    let info = manager.certificate_info().await.unwrap();
+    assert_ne!(info.serial, serial);
+    assert_eq!(info.key_algorithm, KeyAlgorithm::Rsa);
+    assert_eq!(info.key_bits, 2048);
 }
 
 #[tokio::test]
@@ -149,7 +414,7 @@ async fn test_slack_notification_without_webhook() {
     let (manager, _temp_dir) = create_test_manager().await;
 
     // Should succeed even without webhook configured
-    let result = manager.send_slack_notification("Test message").await;
+    let result = manager.notify(Event::Failed, "Test message").await;
     assert!(result.is_ok());
 }
 
@@ -172,11 +437,1099 @@ async fn test_slack_notification_with_mock_webhook() {
 
     // Create manager with mock webhook URL
     let (mut manager, _temp_dir) = create_test_manager().await;
-    manager.config.slack_webhook_url = Some(format!("{}/webhook", mock_server.uri()));
+    manager.config.notifiers = vec![notifier(
+        "slack",
+        NotifierSettings::Slack,
+        &format!("{}/webhook", mock_server.uri()),
+        Severity::Info,
+    )];
 
     // Send notification
     let result = manager
-        .send_slack_notification("Integration test message")
+        .notify(Event::Renewed, "Integration test message")
         .await;
     assert!(result.is_ok());
+    let requests = mock_server.received_requests().await.unwrap();
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(body["text"], "[integration-test] Integration test message");
+    assert_eq!(body["attachments"][0]["color"], "#2eb886");
+
+    // A rejected notification is an error worth retrying
+    Mock::given(method("POST"))
+        .and(path("/unavailable"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&mock_server)
+        .await;
+    manager.config.notifiers[0].url = format!("{}/unavailable", mock_server.uri());
+    let error = manager
+        .notify(Event::Renewed, "Integration test message")
+        .await
+        .unwrap_err();
+    assert!(matches!(&error, Error::Notification { .. }), "{error}");
+    assert!(error.is_retryable());
+}
+
+/// Certificate parameters for the token `sans` and step-ca `template` data.
+fn template_params(sans: &Value, template: &Value) -> rcgen::CertificateParams {
+    use rcgen::{CertificateParams, DistinguishedName, DnType, ExtendedKeyUsagePurpose};
+
+    let sans = sans
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|san| san.as_str().unwrap().to_owned())
+        .collect::<Vec<_>>();
+    let mut params = CertificateParams::new(sans).unwrap();
+    params.distinguished_name = DistinguishedName::new();
+    for (field, dn_type) in [
+        ("country", DnType::CountryName),
+        ("province", DnType::StateOrProvinceName),
+        ("locality", DnType::LocalityName),
+        ("organization", DnType::OrganizationName),
+        ("organizationalUnit", DnType::OrganizationalUnitName),
+        ("commonName", DnType::CommonName),
+    ] {
+        if let Some(value) = template["subject"][field].as_str() {
+            params.distinguished_name.push(dn_type, value);
+        }
+    }
+    for usage in template["extKeyUsage"].as_array().unwrap() {
+        match usage.as_str().unwrap() {
+            "serverAuth" => params.insert_extended_key_usage(ExtendedKeyUsagePurpose::ServerAuth),
+            "clientAuth" => params.insert_extended_key_usage(ExtendedKeyUsagePurpose::ClientAuth),
+            _ => {}
+        }
+    }
+    params
+}
+
+/// A configured notification sink.
+fn notifier(
+    name: &str,
+    settings: NotifierSettings,
+    url: &str,
+    min_severity: Severity,
+) -> NotifierSpec {
+    NotifierSpec {
+        name: name.to_owned(),
+        url: url.to_owned(),
+        min_severity,
+        escalation: false,
+        settings,
+    }
+}
+
+/// A sink outside the crate that records what it is sent.
+struct RecordingNotifier {
+    sent: Arc<Mutex<Vec<Notification>>>,
+}
+
+impl Notifier for RecordingNotifier {
+    fn name(&self) -> &'static str {
+        "recorder"
+    }
+
+    fn send<'a>(
+        &'a self,
+        notification: &'a Notification,
+    ) -> BoxFuture<'a, dimension_bridge::Result<()>> {
+        self.sent.lock().unwrap().push(notification.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_notifications_are_routed_by_severity() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/down"))
+        .respond_with(ResponseTemplate::new(502))
+        .mount(&mock_server)
+        .await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&mock_server)
+        .await;
+
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.notifiers = vec![
+        notifier(
+            "team",
+            NotifierSettings::Slack,
+            &format!("{}/team", mock_server.uri()),
+            Severity::Info,
+        ),
+        notifier(
+            "oncall",
+            NotifierSettings::Webhook(WebhookSpec::default()),
+            &format!("{}/oncall", mock_server.uri()),
+            Severity::Error,
+        ),
+    ];
+
+    manager
+        .notify(Event::Renewed, "✅ Certificate renewed")
+        .await
+        .unwrap();
+    manager
+        .notify(Event::Failed, "❌ Reload failed")
+        .await
+        .unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    let paths: Vec<_> = requests.iter().map(|r| r.url.path()).collect();
+    assert_eq!(paths.iter().filter(|p| **p == "/team").count(), 2);
+    assert_eq!(paths.iter().filter(|p| **p == "/oncall").count(), 1);
+    let oncall = requests.iter().find(|r| r.url.path() == "/oncall").unwrap();
+    let body: Value = serde_json::from_slice(&oncall.body).unwrap();
+    assert_eq!(body["service"], "integration-test");
+    assert_eq!(body["severity"], "error");
+    assert_eq!(body["message"], "❌ Reload failed");
+
+    // A failing sink does not keep the others from being notified
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let failing = dimension_bridge::notifier::WebhookNotifier::new(
+        "down",
+        &format!("{}/down", mock_server.uri()),
+        manager.http_client.clone(),
+    );
+    manager.notifiers = Some(vec![
+        Sink::new(Arc::new(failing), Severity::Info),
+        Sink::new(
+            Arc::new(RecordingNotifier {
+                sent: Arc::clone(&sent),
+            }),
+            Severity::Warning,
+        ),
+    ]);
+    let error = manager
+        .notify(Event::Fallback, "⚠️ Fallback deployed")
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&error, Error::Notification { channel, .. } if channel == "down"),
+        "{error}"
+    );
+    let severities: Vec<_> = sent.lock().unwrap().iter().map(|n| n.severity).collect();
+    assert_eq!(severities, [Severity::Warning]);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_expiry_warnings_are_sent_once_per_threshold() {
+    let (mut manager, temp_dir) = create_test_manager().await;
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    manager.notifiers = Some(vec![Sink::new(
+        Arc::new(RecordingNotifier {
+            sent: Arc::clone(&sent),
+        }),
+        Severity::Info,
+    )]);
+    manager.issuers = Some(vec![Arc::new(SelfSignedIssuer)]);
+    manager.config.cert_validity = Duration::days(10);
+    manager.config.expiry_warnings =
+        vec![Duration::days(30), Duration::days(14), Duration::days(7)];
+    let events = || -> Vec<(Event, String)> {
+        sent.lock()
+            .unwrap()
+            .iter()
+            .map(|n| (n.event, n.message.clone()))
+            .collect()
+    };
+
+    // A fresh 10-day certificate is past 30d and 14d: one warning, at 14d
+    manager.run_once().await.unwrap();
+    let sent_events = events();
+    assert_eq!(sent_events.len(), 2, "{sent_events:?}");
+    assert_eq!(sent_events[0].0, Event::Renewed);
+    assert_eq!(sent_events[1].0, Event::Expiring);
+    assert!(
+        sent_events[1].1.contains("(warning at 14d)"),
+        "{sent_events:?}"
+    );
+
+    // Not repeated, even after a restart
+    manager.warn_before_expiry().await.unwrap();
+    let mut restarted = CertManager::new(manager.config.clone());
+    restarted.notifiers.clone_from(&manager.notifiers);
+    restarted.warn_before_expiry().await.unwrap();
+    assert_eq!(events().len(), 2);
+
+    // A threshold added later is warned about once crossed
+    manager.config.expiry_warnings =
+        vec![Duration::days(14), Duration::days(12), Duration::days(7)];
+    manager.warn_before_expiry().await.unwrap();
+    assert_eq!(events().len(), 3);
+    assert!(events()[2].1.contains("(warning at 12d)"));
+
+    // A new certificate starts over
+    fs::remove_file(temp_dir.path().join("certs/integration-test.crt")).unwrap();
+    manager.run_once().await.unwrap();
+    let sent_events = events();
+    assert_eq!(sent_events.len(), 5, "{sent_events:?}");
+    assert_eq!(sent_events[4].0, Event::Expiring);
+
+    // Warnings count against the rate limit, and a dropped one is sent later
+    manager.config.notify_rate_limit = Some("1/1h".parse().unwrap());
+    manager.config.expiry_warnings =
+        vec![Duration::days(14), Duration::days(10), Duration::days(7)];
+    manager.warn_before_expiry().await.unwrap();
+    manager.config.expiry_warnings =
+        vec![Duration::days(14), Duration::days(11), Duration::days(10)];
+    manager.warn_before_expiry().await.unwrap();
+    assert_eq!(events().len(), 6);
+    let mut restarted = CertManager::new(manager.config.clone());
+    restarted.notifiers.clone_from(&manager.notifiers);
+    restarted.warn_before_expiry().await.unwrap();
+    assert_eq!(events().len(), 7);
+    assert!(events()[6].1.contains("(warning at 11d)"));
+}
+
+/// A sink that cannot be reached.
+struct UnreachableNotifier;
+
+impl Notifier for UnreachableNotifier {
+    fn name(&self) -> &'static str {
+        "unreachable"
+    }
+
+    fn send<'a>(
+        &'a self,
+        _notification: &'a Notification,
+    ) -> BoxFuture<'a, dimension_bridge::Result<()>> {
+        Box::pin(async {
+            Err(Error::Notification {
+                channel: "unreachable".to_owned(),
+                reason: "connection refused".to_owned(),
+            })
+        })
+    }
+}
+
+#[tokio::test]
+#[serial]
+async fn test_expiry_warning_is_not_repeated_when_a_sink_fails() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    manager.issuers = Some(vec![Arc::new(SelfSignedIssuer)]);
+    manager.config.cert_validity = Duration::days(10);
+    manager.run_once().await.unwrap();
+
+    manager.notifiers = Some(vec![
+        Sink::new(Arc::new(UnreachableNotifier), Severity::Info),
+        Sink::new(
+            Arc::new(RecordingNotifier {
+                sent: Arc::clone(&sent),
+            }),
+            Severity::Info,
+        ),
+    ]);
+    manager.config.expiry_warnings = vec![Duration::days(14)];
+    manager.warn_before_expiry().await.unwrap();
+    assert_eq!(sent.lock().unwrap().len(), 1);
+
+    // The sink that got the warning does not get it again
+    manager.warn_before_expiry().await.unwrap();
+    assert_eq!(sent.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_renewal_is_not_failed_by_an_unreachable_sink() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.notifiers = Some(vec![Sink::new(
+        Arc::new(UnreachableNotifier),
+        Severity::Info,
+    )]);
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_webhook_posts_signed_template() {
+    use dimension_bridge::notifier::webhook::signature;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/incident"))
+        .and(header("x-team", "platform"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&mock_server)
+        .await;
+
+    let (mut manager, temp_dir) = create_test_manager().await;
+    let secret_file = temp_dir.path().join("webhook.key");
+    fs::write(&secret_file, "hmac-key\n").unwrap();
+    let webhook = WebhookSpec {
+        template: Some(json!({
+            "title": "{{service}}: {{event}}",
+            "days": "{{days_remaining}}",
+            "serial": "{{serial}}",
+            "fingerprint": "{{fingerprint}}",
+            "issuer": "{{issuer}}",
+        })),
+        headers: vec![("X-Team".to_owned(), "platform".to_owned())],
+        secret_file: Some(secret_file.to_str().unwrap().to_owned()),
+        ..WebhookSpec::default()
+    };
+    manager.config.notifiers = vec![notifier(
+        "incident",
+        NotifierSettings::Webhook(webhook),
+        &format!("{}/incident", mock_server.uri()),
+        Severity::Info,
+    )];
+    manager.issuers = Some(vec![Arc::new(SelfSignedIssuer)]);
+
+    manager.run_once().await.unwrap();
+
+    let requests = mock_server.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let body: Value = serde_json::from_slice(&requests[0].body).unwrap();
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(body["title"], "integration-test: renewed");
+    assert_eq!(body["serial"], info.serial);
+    assert_eq!(body["fingerprint"], info.fingerprint_sha256);
+    assert_eq!(body["issuer"], "self-signed");
+    // A lone placeholder keeps its type
+    let days = info.validity.remaining(chrono::Utc::now()).num_days();
+    assert!(body["days"].as_i64().unwrap().abs_diff(days) <= 1, "{body}");
+
+    // The signature covers the exact bytes sent, with the trimmed key
+    let sent = String::from_utf8(requests[0].body.clone()).unwrap();
+    assert_eq!(
+        requests[0].headers.get("x-signature-256").unwrap(),
+        signature("hmac-key", &sent).as_str()
+    );
+
+    // A missing key fails the delivery like an unreachable receiver
+    fs::remove_file(&secret_file).unwrap();
+    let error = manager
+        .notify(Event::Renewed, "Integration test message")
+        .await
+        .unwrap_err();
+    assert!(matches!(&error, Error::Notification { .. }), "{error}");
+    assert!(error.to_string().contains("webhook.key"), "{error}");
+}
+
+/// A local SMTP server that accepts one session and returns the commands and
+/// message lines it received.
+fn smtp_sink() -> (u16, std::thread::JoinHandle<Vec<String>>) {
+    use std::io::{BufRead, BufReader, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let session = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut reader = BufReader::new(stream.try_clone().unwrap());
+        let mut lines = Vec::new();
+        let mut in_data = false;
+        stream.write_all(b"220 sink ESMTP\r\n").unwrap();
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap() == 0 {
+                break;
+            }
+            let line = line.trim_end().to_owned();
+            let command = line.to_ascii_uppercase();
+            lines.push(line);
+            let reply: &[u8] = if in_data {
+                if command != "." {
+                    continue;
+                }
+                in_data = false;
+                b"250 queued\r\n"
+            } else if command.starts_with("EHLO") {
+                b"250-sink\r\n250 AUTH PLAIN\r\n"
+            } else if command.starts_with("AUTH") {
+                b"235 authenticated\r\n"
+            } else if command == "DATA" {
+                in_data = true;
+                b"354 go ahead\r\n"
+            } else if command == "QUIT" {
+                b"221 bye\r\n"
+            } else {
+                b"250 ok\r\n"
+            };
+            stream.write_all(reply).unwrap();
+        }
+        lines
+    });
+    (port, session)
+}
+
+#[tokio::test]
+#[serial]
+async fn test_email_notification_with_local_smtp_sink() {
+    use base64::{Engine, engine::general_purpose::STANDARD};
+    use dimension_bridge::notifier::{EmailSpec, SmtpTls};
+
+    let (port, session) = smtp_sink();
+    let (mut manager, temp_dir) = create_test_manager().await;
+    let password_file = temp_dir.path().join("smtp.pass");
+    fs::write(&password_file, "hunter2\n").unwrap();
+    let email = EmailSpec {
+        host: "127.0.0.1".to_owned(),
+        port,
+        tls: SmtpTls::Plain,
+        from: "Certificates <certs@example.com>".parse().unwrap(),
+        to: vec![
+            "ops@example.com".parse().unwrap(),
+            "sre@example.com".parse().unwrap(),
+        ],
+        username: Some("certs".to_owned()),
+        password_file: Some(password_file.to_str().unwrap().to_owned()),
+    };
+    manager.config.notifiers = vec![notifier(
+        "mail",
+        NotifierSettings::Email(email),
+        &format!("smtp://127.0.0.1:{port}"),
+        Severity::Info,
+    )];
+
+    manager
+        .notify(Event::Failed, "Reload failed")
+        .await
+        .unwrap();
+
+    let lines = session.join().unwrap();
+    let has = |prefix: &str| lines.iter().any(|line| line.starts_with(prefix));
+    let credentials = STANDARD.encode("\0certs\0hunter2");
+    assert!(has(&format!("AUTH PLAIN {credentials}")), "{lines:#?}");
+    assert!(has("MAIL FROM:<certs@example.com>"), "{lines:#?}");
+    assert!(has("RCPT TO:<ops@example.com>"), "{lines:#?}");
+    assert!(has("RCPT TO:<sre@example.com>"), "{lines:#?}");
+    assert!(
+        has("Subject: [integration-test] Reload failed"),
+        "{lines:#?}"
+    );
+    assert!(has("Content-Type: multipart/alternative"), "{lines:#?}");
+    assert!(has("Content-Type: text/html"), "{lines:#?}");
+    assert!(has("Event: failed"), "{lines:#?}");
+}
+
+/// A step-ca stand-in: a root CA that signs CSRs posted to `/1.0/sign`.
+struct MockStepCa {
+    server: wiremock::MockServer,
+    root_pem: String,
+    fingerprint: String,
+    issuer: std::sync::Arc<rcgen::Issuer<'static, rcgen::KeyPair>>,
+}
+
+impl MockStepCa {
+    async fn start() -> Self {
+        use rcgen::{BasicConstraints, CertificateParams, DnType, IsCa, Issuer, KeyPair};
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+        use x509_parser::{certification_request::X509CertificationRequest, prelude::FromDer};
+
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "Mock Step CA");
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let key = KeyPair::generate().unwrap();
+        let root_pem = params.self_signed(&key).unwrap().pem();
+        let fingerprint =
+            dimension_bridge::certificate::parse_certificate_info(root_pem.as_bytes())
+                .unwrap()
+                .fingerprint_sha256;
+        let issuer = std::sync::Arc::new(Issuer::new(params, key));
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path(format!("/root/{fingerprint}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "ca": root_pem })))
+            .mount(&server)
+            .await;
+
+        let signer = std::sync::Arc::clone(&issuer);
+        let ca_pem = root_pem.clone();
+        Mock::given(method("POST"))
+            .and(path("/1.0/sign"))
+            .respond_with(move |request: &wiremock::Request| {
+                let body: Value = serde_json::from_slice(&request.body).unwrap();
+                let claims = token_claims(body["ott"].as_str().unwrap());
+                if claims["iss"] != "admin" {
+                    return ResponseTemplate::new(401)
+                        .set_body_json(json!({ "status": 401, "message": "invalid token" }));
+                }
+
+                // Like a templated step-ca: the key comes from the CSR, the
+                // rest from the token and the template data
+                let (_, pem) =
+                    x509_parser::pem::parse_x509_pem(body["csr"].as_str().unwrap().as_bytes())
+                        .unwrap();
+                let (_, csr) = X509CertificationRequest::from_der(&pem.contents).unwrap();
+                let public_key = rcgen::SubjectPublicKeyInfo::from_der(
+                    csr.certification_request_info.subject_pki.raw,
+                )
+                .unwrap();
+                let mut params = template_params(&claims["sans"], &body["templateData"]);
+                params.not_before = time::OffsetDateTime::now_utc();
+                params.not_after = time::OffsetDateTime::now_utc() + time::Duration::days(15);
+                let crt = params.signed_by(&public_key, &signer).unwrap().pem();
+
+                ResponseTemplate::new(201).set_body_json(json!({
+                    "crt": crt,
+                    "ca": ca_pem,
+                    "certChain": [crt, ca_pem],
+                }))
+            })
+            .mount(&server)
+            .await;
+
+        Self {
+            server,
+            root_pem,
+            fingerprint,
+            issuer,
+        }
+    }
+
+    /// Sign a certificate for an existing key, valid for `valid_days`.
+    fn sign_for(&self, key: &rcgen::KeyPair, valid_days: i64) -> String {
+        let mut params =
+            rcgen::CertificateParams::new(vec!["localhost".to_owned(), "127.0.0.1".to_owned()])
+                .unwrap();
+        params.not_before = time::OffsetDateTime::now_utc() - time::Duration::days(10);
+        params.not_after = time::OffsetDateTime::now_utc() + time::Duration::days(valid_days);
+        params.signed_by(key, &self.issuer).unwrap().pem()
+    }
+
+    /// Step CA settings for the built-in client, with a plain JWK provisioner key.
+    fn config(&self, dir: &std::path::Path) -> StepCaConfig {
+        use aws_lc_rs::{
+            encoding::AsBigEndian,
+            signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair, KeyPair},
+        };
+        use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+        let key = EcdsaKeyPair::generate(&ECDSA_P256_SHA256_FIXED_SIGNING).unwrap();
+        let point = key.public_key().as_ref();
+        let jwk = json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "kid": "mock-kid",
+            "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+            "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+            "d": URL_SAFE_NO_PAD.encode(key.private_key().as_be_bytes().unwrap().as_ref()),
+        });
+        let key_file = dir.join("provisioner.json");
+        fs::write(&key_file, jwk.to_string()).unwrap();
+
+        StepCaConfig {
+            url: self.server.uri(),
+            provisioner: Some("admin".to_owned()),
+            password_file: None,
+            provisioner_key_file: Some(key_file.to_str().unwrap().to_owned()),
+            token_file: None,
+            root_fingerprint: Some(self.fingerprint.clone()),
+            root_cert: None,
+        }
+    }
+}
+
+/// Decode the claims of a JWT without verifying it.
+fn token_claims(token: &str) -> Value {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+    let claims = token.split('.').nth(1).unwrap();
+    serde_json::from_slice(&URL_SAFE_NO_PAD.decode(claims).unwrap()).unwrap()
+}
+
+#[tokio::test]
+#[serial]
+async fn test_builtin_client_enrolls_without_step_binary() {
+    let ca = MockStepCa::start().await;
+    let (mut manager, temp_dir) = create_test_manager().await;
+    manager.config.step_ca = Some(ca.config(temp_dir.path()));
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(info.issuer, "CN=Mock Step CA");
+    assert_eq!(
+        info.subject,
+        "C=KR, O=integration-test Service, CN=127.0.0.1"
+    );
+
+    let chain = fs::read_to_string(temp_dir.path().join("certs/integration-test.crt")).unwrap();
+    assert_eq!(chain.matches("BEGIN CERTIFICATE").count(), 2);
+    assert_eq!(
+        fs::read_to_string(temp_dir.path().join("certs/root_ca.crt")).unwrap(),
+        ca.root_pem
+    );
+
+    let requests = ca.server.received_requests().await.unwrap();
+    let sign = requests
+        .iter()
+        .find(|request| request.url.path() == "/1.0/sign")
+        .unwrap();
+    let body: Value = serde_json::from_slice(&sign.body).unwrap();
+    let claims = token_claims(body["ott"].as_str().unwrap());
+    assert_eq!(claims["sub"], "127.0.0.1");
+    assert_eq!(claims["aud"], format!("{}/1.0/sign", ca.server.uri()));
+    assert_eq!(claims["sans"], json!(["localhost", "127.0.0.1"]));
+    assert_eq!(claims["sha"], ca.fingerprint);
+    assert_eq!(body["notAfter"], "360h");
+    assert_eq!(body["templateData"]["extKeyUsage"], json!(["serverAuth"]));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_one_time_token_is_sent_by_builtin_client() {
+    use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+
+    let ca = MockStepCa::start().await;
+    let (mut manager, temp_dir) = create_test_manager().await;
+    // Minted ahead of time, e.g. with `step ca token`
+    let claims = json!({ "iss": "admin", "sans": ["localhost", "127.0.0.1"] });
+    let token = format!(
+        "eyJhbGciOiJFUzI1NiJ9.{}.signature",
+        URL_SAFE_NO_PAD.encode(claims.to_string())
+    );
+    let token_file = temp_dir.path().join("token");
+    fs::write(&token_file, format!("{token}\n")).unwrap();
+    let mut config = ca.config(temp_dir.path());
+    config.provisioner_key_file = None;
+    config.token_file = Some(token_file.to_str().unwrap().to_owned());
+    // `step` would download the root, which holds no secret
+    let root = temp_dir.path().join("root_ca.crt");
+    fs::write(&root, &ca.root_pem).unwrap();
+    config.root_cert = Some(root.to_str().unwrap().to_owned());
+    manager.config.step_ca = Some(config);
+
+    // Enrolls without the step binary, so the token is on no command line
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(info.issuer, "CN=Mock Step CA");
+
+    let requests = ca.server.received_requests().await.unwrap();
+    let sign = requests
+        .iter()
+        .find(|request| request.url.path() == "/1.0/sign")
+        .unwrap();
+    let body: Value = serde_json::from_slice(&sign.body).unwrap();
+    assert_eq!(body["ott"], token);
+}
+
+#[tokio::test]
+#[serial]
+async fn test_builtin_client_requests_profile() {
+    use dimension_bridge::profile::parse_policy_oids;
+    use x509_parser::pem::parse_x509_pem;
+
+    let ca = MockStepCa::start().await;
+    let (mut manager, temp_dir) = create_test_manager().await;
+    manager.config.step_ca = Some(ca.config(temp_dir.path()));
+    manager.config.profile = CertProfile {
+        kind: ProfileKind::ServerClient,
+        subject: "O=Acme, OU=Platform".parse().unwrap(),
+        policy_oids: parse_policy_oids("1.3.6.1.4.1.99999.1").unwrap(),
+    };
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+
+    // The mock CA honors the request, so the certificate shows what was asked for
+    let chain = fs::read(temp_dir.path().join("certs/integration-test.crt")).unwrap();
+    let (_, pem) = parse_x509_pem(&chain).unwrap();
+    let cert = pem.parse_x509().unwrap();
+    assert_eq!(
+        cert.subject().to_string(),
+        "O=Acme, OU=Platform, CN=127.0.0.1"
+    );
+    let eku = cert.extended_key_usage().unwrap().unwrap().value;
+    assert!(eku.server_auth && eku.client_auth);
+
+    let requests = ca.server.received_requests().await.unwrap();
+    let sign = requests
+        .iter()
+        .find(|request| request.url.path() == "/1.0/sign")
+        .unwrap();
+    let body: Value = serde_json::from_slice(&sign.body).unwrap();
+    assert_eq!(
+        body["templateData"],
+        json!({
+            "subject": {
+                "organization": "Acme",
+                "organizationalUnit": "Platform",
+                "commonName": "127.0.0.1",
+            },
+            "keyUsage": ["digitalSignature", "keyEncipherment"],
+            "extKeyUsage": ["serverAuth", "clientAuth"],
+            "basicConstraints": { "isCA": false },
+            "policyIdentifiers": ["1.3.6.1.4.1.99999.1"],
+        })
+    );
+}
+
+#[tokio::test]
+#[serial]
+async fn test_builtin_client_renews_ca_issued_certificate() {
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, ResponseTemplate};
+
+    let ca = MockStepCa::start().await;
+    let (mut manager, temp_dir) = create_test_manager().await;
+    manager.config.step_ca = Some(ca.config(temp_dir.path()));
+
+    let key = rcgen::KeyPair::generate().unwrap();
+    let cert_file = temp_dir.path().join("certs/integration-test.crt");
+    let key_file = temp_dir.path().join("certs/integration-test.key");
+    fs::write(&cert_file, ca.sign_for(&key, 2)).unwrap();
+    fs::write(&key_file, key.serialize_pem()).unwrap();
+
+    let renewed = ca.sign_for(&key, 15);
+    Mock::given(method("POST"))
+        .and(path("/1.0/renew"))
+        .respond_with(
+            ResponseTemplate::new(201).set_body_json(json!({ "crt": renewed, "ca": ca.root_pem })),
+        )
+        .expect(1)
+        .mount(&ca.server)
+        .await;
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+    assert!(
+        fs::read_to_string(&cert_file)
+            .unwrap()
+            .starts_with(&renewed)
+    );
+    assert_eq!(fs::read_to_string(&key_file).unwrap(), key.serialize_pem());
+
+    let requests = ca.server.received_requests().await.unwrap();
+    assert!(!requests.iter().any(|r| r.url.path() == "/1.0/sign"));
+}
+
+#[tokio::test]
+#[serial]
+async fn test_step_ca_client_surfaces_api_errors() {
+    let ca = MockStepCa::start().await;
+    let client = StepCaClient::new(&ca.server.uri(), ca.root_pem.as_bytes()).unwrap();
+
+    // Token claims are only decoded by the mock, the signature is not checked
+    let token = format!(
+        "e30.{}.sig",
+        base64::Engine::encode(
+            &base64::engine::general_purpose::URL_SAFE_NO_PAD,
+            json!({ "iss": "someone-else" }).to_string()
+        )
+    );
+    let err = client
+        .sign("csr", &token, "24h", &json!({}))
+        .await
+        .unwrap_err();
+    assert!(
+        matches!(&err, StepCaError::Api { status: 401, message } if message == "invalid token"),
+        "{err}"
+    );
+
+    let err = StepCaClient::fetch_root(&ca.server.uri(), &"0".repeat(64))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, StepCaError::Api { status: 404, .. }), "{err}");
+}
+
+#[tokio::test]
+#[serial]
+async fn test_every_configured_domain_is_covered() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.subject_alt_names = vec![
+        SubjectAltName::Dns("api.company.com".to_owned()),
+        SubjectAltName::Dns("web.company.com".to_owned()),
+        SubjectAltName::Ip("10.0.0.5".parse().unwrap()),
+    ];
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+
+    let info = manager.certificate_info().await.unwrap();
+    for san in &manager.config.subject_alt_names {
+        assert!(info.subject_alt_names.contains(san), "{san} missing");
+    }
+
+    // Adding a domain makes the deployed certificate outdated
+    manager
+        .config
+        .subject_alt_names
+        .push(SubjectAltName::Dns("admin.company.com".to_owned()));
+    assert_eq!(
+        manager.check_cert_status().await,
+        CertStatus::Outdated {
+            reason: "certificate does not cover DNS:admin.company.com".to_owned()
+        }
+    );
+}
+
+#[tokio::test]
+async fn test_extra_sans_can_be_disabled() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.extra_subject_alt_names = Vec::new();
+    manager.config.subject_alt_names = vec![
+        SubjectAltName::Dns("*.company.com".to_owned()),
+        SubjectAltName::Ip("2001:db8::5".parse().unwrap()),
+        SubjectAltName::Uri("spiffe://company.com/web".to_owned()),
+        SubjectAltName::Email("ops@company.com".to_owned()),
+    ];
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(info.subject_alt_names, manager.config.subject_alt_names);
+}
+
+#[tokio::test]
+async fn test_invalid_san_is_not_issued() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.subject_alt_names = vec![SubjectAltName::Dns("api.*.company.com".to_owned())];
+
+    let error = manager.run_once().await.unwrap_err();
+    assert!(error.to_string().contains("api.*.company.com"), "{error}");
+    assert!(matches!(error, Error::Config(_)));
+    assert!(!error.is_retryable());
+    assert!(manager.certificate_info().await.is_err());
+}
+
+#[tokio::test]
+async fn test_failed_reload_is_reported_and_retried() {
+    let (mut manager, temp_dir) = create_test_manager().await;
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    manager.notifiers = Some(vec![Sink::new(
+        Arc::new(RecordingNotifier {
+            sent: Arc::clone(&sent),
+        }),
+        Severity::Info,
+    )]);
+    manager.config.reload_command = Some("echo 'nginx not running' >&2; exit 3".to_owned());
+
+    let error = manager.run_once().await.unwrap_err();
+    assert!(
+        matches!(&error, Error::Reload { reason, .. } if reason.contains("nginx not running")),
+        "{error}"
+    );
+    assert!(!error.is_retryable());
+    // The renewal is announced all the same
+    assert_eq!(sent.lock().unwrap()[0].event, Event::Renewed);
+
+    // The certificate itself was deployed
+    let deployed = manager.certificate_info().await.unwrap();
+
+    // The valid certificate does not hide that the service still runs the old one
+    let error = manager.run_once().await.unwrap_err();
+    assert!(matches!(&error, Error::Reload { .. }), "{error}");
+
+    manager.config.reload_command = Some("true".to_owned());
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+    assert!(
+        !temp_dir
+            .path()
+            .join("certs/integration-test.reload-pending")
+            .exists()
+    );
+    let info = manager.certificate_info().await.unwrap();
+    assert_eq!(info.serial, deployed.serial);
+}
+
+/// A backend from outside the crate, recording which issuers were asked.
+struct InHouseIssuer {
+    name: &'static str,
+    online: bool,
+    asked: Arc<Mutex<Vec<String>>>,
+}
+
+impl Issuer for InHouseIssuer {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn issue<'a>(
+        &'a self,
+        request: &'a IssueRequest,
+    ) -> BoxFuture<'a, dimension_bridge::Result<IssuedCert>> {
+        Box::pin(async move {
+            self.asked.lock().unwrap().push(self.name.to_owned());
+            if !self.online {
+                return Err(Error::Issuance {
+                    backend: self.name.to_owned(),
+                    source: "backend offline".into(),
+                    retryable: true,
+                });
+            }
+            SelfSignedIssuer.issue(request).await
+        })
+    }
+}
+
+#[tokio::test]
+async fn test_issuers_are_asked_in_order() {
+    let (mut manager, _temp_dir) = create_test_manager().await;
+    manager.config.key_type = Some(KeyType::EcdsaP256);
+    let asked = Arc::new(Mutex::new(Vec::new()));
+    let issuer = |name, online| -> Arc<dyn Issuer> {
+        Arc::new(InHouseIssuer {
+            name,
+            online,
+            asked: Arc::clone(&asked),
+        })
+    };
+    manager.issuers = Some(vec![
+        issuer("vault", false),
+        issuer("in-house", true),
+        issuer("unused", true),
+    ]);
+
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+    assert_eq!(*asked.lock().unwrap(), ["vault", "in-house"]);
+
+    // Every issuer failing is reported as a retryable issuance failure
+    asked.lock().unwrap().clear();
+    manager.issuers = Some(vec![issuer("vault", false), issuer("in-house", false)]);
+    // A different key type makes the deployed certificate outdated
+    manager.config.key_type = Some(KeyType::EcdsaP384);
+    let error = manager.run_once().await.unwrap_err();
+    assert!(matches!(error, Error::Issuance { .. }), "{error}");
+    assert!(error.is_retryable());
+    assert!(error.to_string().contains("backend offline"), "{error}");
+    assert_eq!(*asked.lock().unwrap(), ["vault", "in-house"]);
+}
+
+#[tokio::test]
+async fn test_fallback_certificate_is_replaced_once_the_ca_is_back() {
+    let (mut manager, temp_dir) = create_test_manager().await;
+    manager.config.key_type = Some(KeyType::EcdsaP256);
+    let asked = Arc::new(Mutex::new(Vec::new()));
+    let chain = |online| -> Vec<Arc<dyn Issuer>> {
+        vec![
+            Arc::new(InHouseIssuer {
+                name: "ca",
+                online,
+                asked: Arc::clone(&asked),
+            }),
+            Arc::new(SelfSignedIssuer),
+        ]
+    };
+    let marker = temp_dir.path().join("certs/integration-test.fallback");
+
+    // Without a fallback nothing is deployed while the CA is down
+    manager.issuers = Some(chain(false));
+    manager.config.fallback_policy = FallbackPolicy::Forbid;
+    let error = manager.run_once().await.unwrap_err();
+    assert!(error.is_retryable());
+    assert!(error.to_string().contains("self-signed skipped"), "{error}");
+    assert_eq!(manager.check_cert_status().await, CertStatus::Missing);
+
+    // A fallback lets the service start, with its own status
+    manager.config.fallback_policy = FallbackPolicy::AllowIfMissing;
+    let status = manager.run_once().await.unwrap();
+    assert!(
+        matches!(&status, CertStatus::Fallback { issuer, .. } if issuer == "self-signed"),
+        "{status}"
+    );
+    assert_eq!(status.exit_code(), 9);
+    assert!(marker.exists());
+
+    // The CA is asked again at every check, keeping the fallback meanwhile
+    asked.lock().unwrap().clear();
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Fallback { .. }), "{status}");
+    assert_eq!(*asked.lock().unwrap(), ["ca"]);
+
+    // and replaces it once it is back
+    manager.issuers = Some(chain(true));
+    let status = manager.run_once().await.unwrap();
+    assert!(matches!(status, CertStatus::Healthy { .. }), "{status}");
+    assert!(!marker.exists());
+
+    // An existing certificate is not replaced by a fallback
+    manager.issuers = Some(chain(false));
+    manager.config.key_type = Some(KeyType::EcdsaP384);
+    let deployed = fs::read(temp_dir.path().join("certs/integration-test.crt")).unwrap();
+    assert!(manager.run_once().await.is_err());
+    assert_eq!(
+        fs::read(temp_dir.path().join("certs/integration-test.crt")).unwrap(),
+        deployed
+    );
+    assert!(!marker.exists());
+
+    // Not even under the default policy while it is valid but due
+    manager.config.fallback_policy = FallbackPolicy::default();
+    manager.config.key_type = Some(KeyType::EcdsaP256);
+    manager.config.renew_before = manager.config.cert_validity;
+    let error = manager.run_once().await.unwrap_err();
+    assert!(error.is_retryable(), "{error}");
+    assert_eq!(
+        fs::read(temp_dir.path().join("certs/integration-test.crt")).unwrap(),
+        deployed
+    );
+    assert!(!marker.exists());
+}
+
+#[tokio::test]
+async fn test_one_manager_handles_many_certificates() {
+    let (mut manager, temp_dir) = create_test_manager().await;
+    manager.config.max_parallel_renewals = 2;
+    manager.config.certificates = ["api", "web", "admin"]
+        .into_iter()
+        .map(|name| {
+            let cert_dir = temp_dir.path().join(name);
+            CertSpec {
+                name: name.to_owned(),
+                server_ip: format!("{name}.company.com"),
+                subject_alt_names: vec![SubjectAltName::Dns(format!("{name}.company.com"))],
+                cert_dir: cert_dir.to_str().unwrap().to_owned(),
+                cert_validity: Duration::days(30),
+                renew_before: Duration::days(5),
+                renew_at_percent: None,
+                reload_command: Some(format!("touch {}/reloaded", cert_dir.display())),
+                profile: CertProfile::for_service(name),
+            }
+        })
+        .collect();
+    manager.initialize().await.unwrap();
+
+    let results = manager.run_once_all().await;
+    let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+    assert_eq!(names, ["api", "web", "admin"]);
+    for (name, result) in results {
+        let status = result.unwrap();
+        assert!(
+            matches!(status, CertStatus::Healthy { .. }),
+            "{name}: {status}"
+        );
+
+        let cert_dir = temp_dir.path().join(&name);
+        let deployed = fs::read(cert_dir.join(format!("{name}.crt"))).unwrap();
+        let info = dimension_bridge::certificate::parse_certificate_info(&deployed).unwrap();
+        assert!(info.subject.ends_with(&format!("CN={name}.company.com")));
+        assert_eq!(
+            (info.validity.not_after - info.validity.not_before).num_days(),
+            30
+        );
+        assert!(cert_dir.join("reloaded").exists(), "{name} not reloaded");
+    }
+
+    // The top-level certificate only provides defaults
+    assert!(manager.certificate_info().await.is_err());
+
+    let statuses = manager.check_all().await;
+    assert_eq!(statuses.len(), 3);
+    assert!(
+        statuses
+            .iter()
+            .all(|(_, status)| matches!(status, CertStatus::Healthy { .. }))
+    );
 }